@@ -0,0 +1,60 @@
+#[macro_use]
+extern crate log;
+extern crate env_logger;
+
+#[macro_use]
+extern crate clap;
+
+extern crate chipate;
+
+#[path = "../../shared/cli.rs"]
+mod cli;
+
+use std::fs::File;
+use std::io::{self, prelude::*};
+use std::path::Path;
+
+use chipate::asm;
+use cli::{fail, parse_address};
+
+fn main() {
+    env_logger::init().unwrap();
+    let matches = clap_app!(chipate_asm =>
+                            (version: "1.0")
+                            (author: "Robert J. Lambert III <rlambert85@gmail.com>")
+                            (about: "Assembles Octo source into a Chip-8 ROM")
+                            (@arg source: +required "Octo source to assemble, or - for stdin")
+                            (@arg output: -o --output +takes_value "Where to write the ROM (default the source name with a .ch8 extension)")
+                            (@arg load_address: --("load-address") +takes_value "Where the ROM will be loaded, 0x200 by default")
+    )
+            .get_matches();
+
+    let source = matches.value_of("source").unwrap();
+    let origin = match matches.value_of("load_address") {
+        Some(addr) => parse_address(addr).unwrap_or_else(|| fail(format!("Invalid load address {}", addr))),
+        None => 0x200,
+    };
+
+    let text = read_source(source).unwrap_or_else(|e| fail(format!("Unable to read {}: {}", source, e)));
+    let rom = asm::assemble_at(&text, origin).unwrap_or_else(|e| fail(format!("{}:{}: {}", source, e.line, e.message)));
+    debug!("Assembled {} bytes at 0x{:03X}", rom.len(), origin);
+
+    let output = match matches.value_of("output") {
+        Some(path) => path.to_string(),
+        None if source == "-" => fail("Reading from stdin needs --output"),
+        None => Path::new(source).with_extension("ch8").to_string_lossy().into_owned(),
+    };
+    if let Err(e) = File::create(&output).and_then(|mut f| f.write_all(&rom)) {
+        fail(format!("Unable to write {}: {}", output, e));
+    }
+}
+
+fn read_source(path: &str) -> io::Result<String> {
+    let mut s = String::new();
+    if path == "-" {
+        io::stdin().read_to_string(&mut s)?;
+    } else {
+        File::open(path)?.read_to_string(&mut s)?;
+    }
+    Ok(s)
+}
@@ -0,0 +1,58 @@
+#[macro_use]
+extern crate log;
+extern crate env_logger;
+
+#[macro_use]
+extern crate clap;
+
+extern crate chipate;
+
+#[path = "../../shared/cli.rs"]
+mod cli;
+
+use std::fs::File;
+use std::io::{self, prelude::*};
+
+use chipate::disasm::Listing;
+use cli::{fail, parse_address};
+
+fn main() {
+    env_logger::init().unwrap();
+    let matches = clap_app!(chipate_dis =>
+                            (version: "1.0")
+                            (author: "Robert J. Lambert III <rlambert85@gmail.com>")
+                            (about: "Disassembles a Chip-8 ROM into Octo assembly")
+                            (@arg rom: +required "ROM to disassemble, or - for stdin")
+                            (@arg output: -o --output +takes_value "Where to write the assembly (default stdout)")
+                            (@arg load_address: --("load-address") +takes_value "Where the ROM is loaded and entered, 0x200 by default")
+    )
+            .get_matches();
+
+    let rom = matches.value_of("rom").unwrap();
+    let origin = match matches.value_of("load_address") {
+        Some(addr) => parse_address(addr).unwrap_or_else(|| fail(format!("Invalid load address {}", addr))),
+        None => 0x200,
+    };
+
+    let bytes = read_rom(rom).unwrap_or_else(|e| fail(format!("Unable to read {}: {}", rom, e)));
+    debug!("Disassembling {} bytes at 0x{:03X}", bytes.len(), origin);
+    let source = Listing::new(&bytes, origin).to_octo();
+
+    let written = match matches.value_of("output") {
+        Some(path) => File::create(path).and_then(|mut f| f.write_all(source.as_bytes())),
+        None => io::stdout().write_all(source.as_bytes()),
+    };
+    if let Err(e) = written {
+        fail(e);
+    }
+}
+
+fn read_rom(path: &str) -> io::Result<Vec<u8>> {
+    let mut b = Vec::new();
+    if path == "-" {
+        io::stdin().read_to_end(&mut b)?;
+    } else {
+        File::open(path)?.read_to_end(&mut b)?;
+    }
+    Ok(b)
+}
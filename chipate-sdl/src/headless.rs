@@ -0,0 +1,85 @@
+use chipate::audio::{ToneGenerator, WavSink, Waveform};
+use chipate::{Chipate, EmuError, InputSource, Movie};
+
+use speaker::SAMPLE_RATE;
+
+/// Press
+/// A keypad key held down from one frame to another, both included.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Press {
+    from: usize,
+    to: usize,
+    key: usize,
+}
+
+impl Press {
+    /// Parses `FRAME:KEY` or `FROM-TO:KEY`, frames in decimal and the key
+    /// as a hex digit, e.g. `120-125:5`.
+    pub fn parse(s: &str) -> Result<Press, String> {
+        let invalid = || format!("Invalid key press {}, expected FRAME:KEY or FROM-TO:KEY", s);
+        let mut parts = s.splitn(2, ':');
+        let frames = parts.next().unwrap();
+        let key = parts.next().ok_or_else(invalid)?;
+
+        let key = usize::from_str_radix(key, 16).ok().filter(|&k| k < 16).ok_or_else(invalid)?;
+        let frame = |f: &str| f.parse::<usize>().map_err(|_| invalid());
+        let (from, to) = match frames.find('-') {
+            Some(n) => (frame(&frames[..n])?, frame(&frames[n + 1..])?),
+            None => (frame(frames)?, frame(frames)?),
+        };
+        Ok(Press { from, to, key })
+    }
+}
+
+/// Feeds the scripted presses for one frame.
+struct Script<'p> {
+    presses: &'p [Press],
+    frame: usize,
+}
+
+impl<'p> InputSource for Script<'p> {
+    fn poll(&mut self, key: &mut [u8; 16]) {
+        *key = [0; 16];
+        for p in self.presses.iter().filter(|p| (p.from..=p.to).contains(&self.frame)) {
+            key[p.key] = 1;
+        }
+    }
+}
+
+/// Records the beep as the speaker would play it.
+pub fn wav_sink(frequency: f32, volume: f32, waveform: Waveform) -> WavSink {
+    let mut tone = ToneGenerator::new(SAMPLE_RATE);
+    tone.set_frequency(frequency);
+    tone.set_volume(volume);
+    tone.set_waveform(waveform);
+    WavSink::new(tone)
+}
+
+/// Runs `frames` frames as fast as possible with no window, each through
+/// `run_frame`. Input comes from the movie while it lasts and from `presses`
+/// otherwise. Every frame of sound goes to `wav`.
+pub fn run<F>(chip: &mut Chipate,
+              frames: usize,
+              presses: &[Press],
+              replay: Option<&Movie>,
+              mut wav: Option<&mut WavSink>,
+              mut run_frame: F)
+              -> Result<(), EmuError>
+    where F: FnMut(&mut Chipate) -> Result<(), EmuError>
+{
+    for frame in 0..frames {
+        if chip.exited() {
+            debug!("Program exited after {} frames", frame);
+            break;
+        }
+        let replayed = replay.is_some_and(|m| m.apply_frame(frame, chip));
+        if !replayed {
+            chip.set_keys(&mut Script { presses, frame });
+        }
+        run_frame(chip)?;
+        if let Some(ref mut w) = wav {
+            chip.play_sound(*w);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,159 @@
+use sdl::event::{self, Event, Key, Mod};
+
+use chipate::keymap::Keymap;
+use chipate::InputSource;
+
+/// Save state hotkeys: F1 to F9 load that slot, with Shift they save it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StateRequest {
+    Save(u8),
+    Load(u8),
+}
+
+/// Keyboard
+/// Turns SDL key events into keypad state through a `Keymap`, and notices
+/// when the window is closed or resized, Escape is pressed, a save state
+/// hotkey is hit or Backspace is held down to rewind.
+pub struct Keyboard {
+    keymap: Keymap,
+    keys: [u8; 16],
+    quit: bool,
+    state_request: Option<StateRequest>,
+    rewind: bool,
+    resize: Option<(isize, isize)>,
+}
+
+impl Keyboard {
+    pub fn new(keymap: Keymap) -> Keyboard {
+        Keyboard {
+            keymap,
+            keys: [0; 16],
+            quit: false,
+            state_request: None,
+            rewind: false,
+            resize: None,
+        }
+    }
+
+    /// True once the user asked to close the emulator.
+    pub fn quit_requested(&self) -> bool {
+        self.quit
+    }
+
+    /// The save state hotkey pressed since the last call, if any.
+    pub fn take_state_request(&mut self) -> Option<StateRequest> {
+        self.state_request.take()
+    }
+
+    /// The latest window size the user dragged the window to, if it changed
+    /// since the last call.
+    pub fn take_resize(&mut self) -> Option<(isize, isize)> {
+        self.resize.take()
+    }
+
+    /// True while Backspace is held down.
+    pub fn rewinding(&self) -> bool {
+        self.rewind
+    }
+
+    fn handle(&mut self, e: Event) {
+        match e {
+            Event::Quit => self.quit = true,
+            Event::Resize(w, h) => self.resize = Some((w, h)),
+            Event::Key(Key::Escape, true, _, _) => self.quit = true,
+            Event::Key(Key::Backspace, pressed, _, _) => self.rewind = pressed,
+            Event::Key(k, true, ref mods, _) if slot(k).is_some() => {
+                let n = slot(k).unwrap();
+                let shift = mods.iter().any(|m| *m == Mod::LShift || *m == Mod::RShift);
+                self.state_request = Some(if shift { StateRequest::Save(n) } else { StateRequest::Load(n) });
+            }
+            Event::Key(k, pressed, _, _) => {
+                let keypad = key_char(k).and_then(|c| self.keymap.keypad_key(c));
+                if let Some(n) = keypad {
+                    self.keys[n] = if pressed { 1 } else { 0 };
+                    debug!("Key {:X} {}", n, if pressed { "down" } else { "up" });
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl InputSource for Keyboard {
+    fn poll(&mut self, key: &mut [u8; 16]) {
+        loop {
+            match event::poll_event() {
+                Event::None => break,
+                e => self.handle(e),
+            }
+        }
+        *key = self.keys;
+    }
+}
+
+/// Save state slot of a function key.
+fn slot(k: Key) -> Option<u8> {
+    let n = match k {
+        Key::F1 => 1,
+        Key::F2 => 2,
+        Key::F3 => 3,
+        Key::F4 => 4,
+        Key::F5 => 5,
+        Key::F6 => 6,
+        Key::F7 => 7,
+        Key::F8 => 8,
+        Key::F9 => 9,
+        _ => return None,
+    };
+    Some(n)
+}
+
+/// The character printed on an SDL key, for the keys a keymap can use.
+fn key_char(k: Key) -> Option<char> {
+    let c = match k {
+        Key::Num0 => '0',
+        Key::Num1 => '1',
+        Key::Num2 => '2',
+        Key::Num3 => '3',
+        Key::Num4 => '4',
+        Key::Num5 => '5',
+        Key::Num6 => '6',
+        Key::Num7 => '7',
+        Key::Num8 => '8',
+        Key::Num9 => '9',
+        Key::A => 'a',
+        Key::B => 'b',
+        Key::C => 'c',
+        Key::D => 'd',
+        Key::E => 'e',
+        Key::F => 'f',
+        Key::G => 'g',
+        Key::H => 'h',
+        Key::I => 'i',
+        Key::J => 'j',
+        Key::K => 'k',
+        Key::L => 'l',
+        Key::M => 'm',
+        Key::N => 'n',
+        Key::O => 'o',
+        Key::P => 'p',
+        Key::Q => 'q',
+        Key::R => 'r',
+        Key::S => 's',
+        Key::T => 't',
+        Key::U => 'u',
+        Key::V => 'v',
+        Key::W => 'w',
+        Key::X => 'x',
+        Key::Y => 'y',
+        Key::Z => 'z',
+        Key::Comma => ',',
+        Key::Period => '.',
+        Key::Slash => '/',
+        Key::Minus => '-',
+        Key::Equals => '=',
+        Key::Space => ' ',
+        _ => return None,
+    };
+    Some(c)
+}
@@ -0,0 +1,470 @@
+extern crate sdl;
+
+#[macro_use]
+extern crate log;
+extern crate env_logger;
+
+#[macro_use]
+extern crate clap;
+
+#[macro_use]
+extern crate lazy_static;
+
+extern crate chipate;
+
+use std::fs::File;
+use std::io::{self, prelude::*, BufWriter};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use clap::{Arg, SubCommand};
+
+#[path = "../../shared/cli.rs"]
+mod cli;
+mod headless;
+mod keyboard;
+#[path = "../../shared/rom.rs"]
+mod rom;
+mod screen;
+mod speaker;
+
+use chipate::{Chipate, Debugger, EmuError, Movie, Quirks, Rewind, Scheduler, State, VideoSink};
+use chipate::audio::{WavSink, Waveform};
+use chipate::dump;
+use chipate::keymap::{self, Keymap};
+use chipate::quirks;
+use chipate::rng;
+use chipate::trace::{self, Differ, Tracer};
+use cli::{fail, parse_address};
+use headless::Press;
+use keyboard::{Keyboard, StateRequest};
+use rom::load_rom;
+use screen::{Screen, ScreenConfig};
+use speaker::Speaker;
+
+fn main() {
+    env_logger::init().unwrap();
+    let matches = clap_app!(myapp =>
+                            (version: "1.0")
+                            (author: "Robert J. Lambert III <rlambert85@gmail.com>")
+                            (about: "Chip8 Emulator written in rust")
+                            (@arg program: -p --program +required +takes_value "Program to run: a ROM path, a name in ./programs, or - for stdin")
+                            (@arg ipf: --ipf +takes_value "Instructions per 60 Hz frame (default 10)")
+                            (@arg hz: --hz +takes_value conflicts_with[ipf] "Instructions per second, instead of --ipf")
+                            (@arg quirks: --quirks +takes_value "Quirks profile: vip (default), chip48, schip or xochip")
+                            (@arg quirk: --quirk +takes_value +multiple "Override a single quirk, e.g. shift_vy=off")
+                            (@arg load_address: --("load-address") +takes_value "Where the ROM is loaded, 0x200 by default or 0x600 for ETI-660 ROMs")
+                            (@arg keymap: --keymap +takes_value "Keyboard keys for keypad keys 0 to F (default x123qweasdzc4rfv)")
+                            (@arg tone: --tone +takes_value "Beep frequency in Hz (default 440)")
+                            (@arg volume: --volume +takes_value "Beep volume from 0 to 1 (default 0.25)")
+                            (@arg waveform: --waveform +takes_value "Beep waveform: square (default), triangle, sawtooth or sine")
+                            (@arg scale: --scale +takes_value "Window pixels per CHIP-8 pixel (default 20)")
+                            (@arg fg: --fg +takes_value "Pixel color as RRGGBB (default ffffff)")
+                            (@arg bg: --bg +takes_value "Background color as RRGGBB (default 000000)")
+                            (@arg palette: --palette +takes_value "Colors for XO-CHIP planes 0 to 3, e.g. 000000,ffffff,aaaaaa,555555")
+                            (@arg fullscreen: --fullscreen "Fill the screen, keeping the aspect ratio")
+                            (@arg config: --config +takes_value "File of name = value lines for scale, fg, bg, palette and fullscreen")
+                            (@arg rpl: --rpl +takes_value "File keeping the SUPER-CHIP RPL flags between runs")
+                            (@arg debug: --debug "Start paused at the debugger prompt on the terminal")
+                            (@arg rewind: --rewind +takes_value "Seconds of play Backspace can rewind (default 30, 0 turns it off)")
+                            (@arg seed: --seed +takes_value "Seed for the random numbers, to repeat a run (default a fresh one)")
+                            (@arg record: --record +takes_value conflicts_with[debug] "Record the keypad into a movie file")
+                            (@arg replay: --replay +takes_value conflicts_with[record debug] "Replay a movie file, the keyboard takes over when it ends")
+                            (@arg trace: --trace +takes_value conflicts_with[debug] "Write a record of every instruction executed to a file")
+                            (@arg trace_format: --("trace-format") +takes_value requires[trace] "Trace records as text (default) or json")
+                            (@arg headless: --headless conflicts_with[debug record] "Run without a window as fast as possible, for scripted tests")
+                            (@arg frames: --frames +takes_value requires[headless] "Frames to run headless (default the length of the --replay movie)")
+                            (@arg press: --press +takes_value +multiple requires[headless] "Hold a keypad key headless, FRAME:KEY or FROM-TO:KEY, e.g. 60-65:5")
+                            (@arg dump: --dump +takes_value requires[headless] "Write the final screen as .png, .pbm or ASCII text, - for stdout")
+                            (@arg wav: --wav +takes_value requires[headless] "Record the beep to a WAV file")
+    )
+            .subcommand(SubCommand::with_name("diff-trace")
+                .about("Run the program against another emulator's trace and stop where they differ")
+                .arg(Arg::with_name("reference")
+                    .required(true)
+                    .help("Trace with the PC, OP, I and V0 to VF ahead of every instruction")))
+            .get_matches();
+
+    let program = matches.value_of("program").unwrap();
+    debug!("Value for program: {}", program);
+
+    let mut replay = matches.value_of("replay")
+        .map(|path| Movie::load(path).unwrap_or_else(|e| fail(format!("Unable to load {}: {}", path, e))));
+
+    let mut scheduler = match (matches.value_of("ipf"), matches.value_of("hz")) {
+        _ if replay.is_some() => Scheduler::with_hz(replay.as_ref().unwrap().hz),
+        (_, Some(hz)) => Scheduler::with_hz(parse_speed(hz)),
+        (Some(ipf), _) => Scheduler::with_ipf(parse_speed(ipf)),
+        (None, None) => Scheduler::with_ipf(10),
+    };
+    debug!("Running at {} Hz", scheduler.hz());
+
+    let quirks = quirks_from_args(&matches).unwrap_or_else(|e| fail(e));
+
+    let mut chip = Chipate::new();
+    chip.init();
+    chip.set_quirks(quirks);
+    // The xochip profile also switches on the 64K memory
+    let profile = matches.value_of("quirks").and_then(Quirks::from_profile);
+    chip.set_xochip(profile == Some(Quirks::xochip()));
+
+    let seed = match matches.value_of("seed") {
+        Some(s) => s.parse::<u64>().unwrap_or_else(|_| fail(format!("Invalid seed {}", s))),
+        None => rng::random_seed(),
+    };
+    info!("Random seed {}", seed);
+    chip.set_seed(seed);
+
+    if let Some(addr) = matches.value_of("load_address") {
+        let addr = parse_address(addr).unwrap_or_else(|| fail(format!("Invalid load address {}", addr)));
+        chip.set_load_address(addr);
+    }
+
+    // A movie brings its own quirks, load address and RPL flags
+    if let Some(ref movie) = replay {
+        movie.setup(&mut chip);
+    }
+
+    // chip.load_program("PONG");
+    if let Err(e) = load_rom(&mut chip, program) {
+        fail(format!("Unable to load {}: {}", program, e));
+    }
+    if let Some(ref movie) = replay {
+        movie.check_rom(&chip).unwrap_or_else(|e| fail(e));
+    }
+
+    let rpl = matches.value_of("rpl");
+    if let (Some(path), None) = (rpl, replay.as_ref()) {
+        chip.set_rpl_flags(load_rpl(path));
+    }
+
+    let mut tracer = matches.value_of("trace").map(|path| open_trace(path, matches.value_of("trace_format")));
+
+    if let Some(diff) = matches.subcommand_matches("diff-trace") {
+        let reference = diff.value_of("reference").unwrap();
+        diff_trace(reference, &mut chip, &mut scheduler, tracer.as_mut()).unwrap_or_else(|e| fail(e));
+        return;
+    }
+
+    let tone = parse_float(matches.value_of("tone").unwrap_or("440"));
+    let volume = parse_float(matches.value_of("volume").unwrap_or("0.25"));
+    let waveform = matches.value_of("waveform").unwrap_or("square");
+    let waveform = Waveform::from_name(waveform)
+        .unwrap_or_else(|| fail(format!("Unknown waveform {}", waveform)));
+
+    if matches.is_present("headless") {
+        let wav = matches.value_of("wav").map(|_| headless::wav_sink(tone, volume, waveform));
+        let result = run_headless(&matches, &mut chip, &mut scheduler, replay.as_ref(), tracer.as_mut(), wav);
+        if let Some(path) = rpl {
+            save_rpl(path, &chip.rpl_flags());
+        }
+        if let Err(e) = result {
+            fail(e);
+        }
+        return;
+    }
+
+    let keymap = Keymap::from_layout(matches.value_of("keymap").unwrap_or(keymap::QWERTY))
+        .unwrap_or_else(|e| fail(e));
+
+    let screen_config = screen_config_from_args(&matches).unwrap_or_else(|e| fail(e));
+
+    sdl::init(&[sdl::InitFlag::Video, sdl::InitFlag::Audio]);
+    let mut screen = Screen::new(screen_config).unwrap_or_else(|e| fail(format!("Unable to open the window: {}", e)));
+    let mut speaker = Speaker::new(tone, volume, waveform);
+    let mut keyboard = Keyboard::new(keymap);
+
+    let record = matches.value_of("record");
+    let mut recording = record.map(|_| Movie::record(&mut chip, scheduler.hz()));
+    let mut frame_number = 0;
+
+    let mut debugger = if matches.is_present("debug") { Some(Debugger::new()) } else { None };
+    let mut rewind = Rewind::with_seconds(parse_speed(matches.value_of("rewind").unwrap_or("30")));
+
+    // A fault the player can still rewind out of, the program stays frozen meanwhile
+    let mut fault: Option<EmuError> = None;
+
+    let mut result = Ok(());
+    while !chip.exited() && !keyboard.quit_requested() && result.is_ok() {
+        chip.set_keys(&mut keyboard);
+        if let Some((w, h)) = keyboard.take_resize() {
+            screen.resize(w, h);
+            screen.render(&chip.display);
+        }
+        let movie_over = match replay {
+            Some(ref movie) => !movie.apply_frame(frame_number, &mut chip),
+            None => false,
+        };
+        if movie_over {
+            info!("Movie ended after {} frames, the keyboard has control", frame_number);
+            replay = None;
+        }
+
+        // Loading a state or rewinding would make the movie impossible to replay
+        let movie_active = recording.is_some() || replay.is_some();
+        if let Some(request) = keyboard.take_state_request() {
+            if movie_active {
+                warn!("{:?} ignored while a movie is recording or playing", request);
+            } else {
+                handle_state_request(&mut chip, program, request);
+            }
+        }
+
+        if keyboard.rewinding() && !movie_active {
+            if rewind.step_back(&mut chip) {
+                fault = None;
+            }
+        } else if fault.is_none() {
+            rewind.push(&chip);
+            let frame = match debugger {
+                Some(ref mut d) => debug_frame(d, &mut scheduler, &mut chip),
+                None => run_frame(&mut scheduler, &mut chip, tracer.as_mut()),
+            };
+            match frame {
+                Err(e) if !rewind.is_empty() => {
+                    eprintln!("Error: {}, hold Backspace to rewind", e);
+                    fault = Some(e);
+                }
+                r => result = r,
+            }
+            if let Some(ref mut movie) = recording {
+                movie.record_frame(&mut chip);
+            }
+            frame_number += 1;
+        }
+
+        if let Some(ref d) = debugger {
+            if d.quit_requested() {
+                break;
+            }
+        }
+        chip.draw_screen(&mut screen);
+        chip.play_sound(&mut speaker);
+        scheduler.wait();
+    }
+
+    if let Some(path) = rpl {
+        save_rpl(path, &chip.rpl_flags());
+    }
+    if let (Some(path), Some(movie)) = (record, recording) {
+        if let Err(e) = movie.save(path) {
+            warn!("Unable to save the movie to {}: {}", path, e);
+        }
+    }
+    if let Some(e) = fault {
+        result = Err(e);
+    }
+
+    sdl::quit();
+
+    if let Err(e) = result {
+        fail(e);
+    }
+}
+
+/// Runs the program with no window for --frames frames, then dumps the
+/// screen and saves the sound. Both are written even when the program
+/// faulted.
+fn run_headless(matches: &clap::ArgMatches,
+                chip: &mut Chipate,
+                scheduler: &mut Scheduler,
+                replay: Option<&Movie>,
+                mut tracer: Option<&mut TraceFile>,
+                mut wav: Option<WavSink>)
+                -> Result<(), EmuError> {
+    let frames = match (matches.value_of("frames"), replay) {
+        (Some(n), _) => n.parse::<usize>().unwrap_or_else(|_| fail(format!("Invalid frame count {}", n))),
+        (None, Some(movie)) => movie.frames.len(),
+        (None, None) => fail("--headless needs --frames or --replay"),
+    };
+    let presses: Vec<Press> = match matches.values_of("press") {
+        Some(values) => values.map(|p| Press::parse(p).unwrap_or_else(|e| fail(e))).collect(),
+        None => Vec::new(),
+    };
+
+    let result = headless::run(chip, frames, &presses, replay, wav.as_mut(), |c| {
+        run_frame(scheduler, c, tracer.as_deref_mut())
+    });
+    match matches.value_of("dump") {
+        Some("-") => print!("{}", dump::ascii(&chip.display)),
+        Some(path) => dump::save(&chip.display, path)?,
+        None => {}
+    }
+    if let (Some(path), Some(wav)) = (matches.value_of("wav"), wav) {
+        wav.save(path)?;
+    }
+    result
+}
+
+/// Runs the program until it leaves the reference trace or the trace ends,
+/// with no window and no keys pressed. Prints where it diverged and exits
+/// with an error status if it did.
+fn diff_trace(path: &str,
+              chip: &mut Chipate,
+              scheduler: &mut Scheduler,
+              mut tracer: Option<&mut TraceFile>)
+              -> Result<(), String> {
+    let mut text = String::new();
+    File::open(path).and_then(|mut f| f.read_to_string(&mut text))
+        .map_err(|e| format!("Unable to read {}: {}", path, e))?;
+    let steps = trace::parse_reference(&text).map_err(|e| format!("{}: {}", path, e))?;
+
+    let mut differ = Differ::new(&steps);
+    let mut result = Ok(());
+    while !differ.done() && !chip.exited() && result.is_ok() {
+        // The tracer only sees instructions that go on to run
+        result = scheduler.run_frame_until(chip, |c| {
+            differ.before(c) || tracer.as_mut().is_some_and(|t| t.before(c))
+        }).map(|_| ());
+    }
+    if let Some(t) = tracer {
+        t.flush(chip).map_err(|e| format!("Unable to write the trace: {}", e))?;
+    }
+    result.map_err(|e| e.to_string())?;
+
+    match differ.finish() {
+        Ok(n) => {
+            println!("All {} steps match {}", n, path);
+            Ok(())
+        }
+        Err(d) => {
+            println!("{}", d);
+            println!("reference: {}", text.lines().nth(d.line - 1).unwrap_or("").trim());
+            print!("chipate:   PC:{:04X} I:{:04X}", chip.pc(), chip.i());
+            for (x, value) in chip.v().iter().enumerate() {
+                print!(" V{:X}:{:02X}", x, value);
+            }
+            println!();
+            process::exit(1);
+        }
+    }
+}
+
+type TraceFile = Tracer<BufWriter<File>>;
+
+fn open_trace(path: &str, format: Option<&str>) -> TraceFile {
+    let format = format.unwrap_or("text");
+    let format = trace::Format::from_name(format)
+        .unwrap_or_else(|| fail(format!("Unknown trace format {}, expected text or json", format)));
+    let file = File::create(path).unwrap_or_else(|e| fail(format!("Unable to create {}: {}", path, e)));
+    Tracer::new(BufWriter::new(file), format)
+}
+
+/// Runs a frame, recording every instruction when tracing. The trace is
+/// flushed after every frame, so rewinding or loading a state in between
+/// never mixes two timelines in one record.
+fn run_frame(scheduler: &mut Scheduler, chip: &mut Chipate, tracer: Option<&mut TraceFile>) -> Result<(), EmuError> {
+    let tracer = match tracer {
+        Some(t) => t,
+        None => return scheduler.run_frame(chip),
+    };
+    let frame = scheduler.run_frame_until(chip, |c| tracer.before(c));
+    tracer.flush(chip)?;
+    frame.map(|_| ())
+}
+
+/// Runs a frame under the debugger, dropping to its prompt whenever it
+/// pauses. The frame clock restarts afterwards so the program does not
+/// race to catch up with the time spent at the prompt.
+fn debug_frame(debugger: &mut Debugger, scheduler: &mut Scheduler, chip: &mut Chipate) -> Result<(), EmuError> {
+    if !scheduler.run_frame_until(chip, |c| debugger.should_break(c))? {
+        return Ok(());
+    }
+
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    debugger.prompt(chip, &mut stdin.lock(), &mut stdout.lock())?;
+    scheduler.restart_clock();
+    Ok(())
+}
+
+/// Saves or loads a numbered slot, failures only get logged so a bad slot
+/// never takes the running program down.
+fn handle_state_request(chip: &mut Chipate, program: &str, request: StateRequest) {
+    let result = match request {
+        StateRequest::Save(n) => chip.save_state().save(state_path(program, n)),
+        StateRequest::Load(n) => State::load(state_path(program, n)).map(|s| chip.load_state(&s)),
+    };
+    match result {
+        Ok(()) => info!("{:?} done", request),
+        Err(e) => warn!("{:?} failed: {}", request, e),
+    }
+}
+
+/// Slot files sit in the current directory, named after the ROM.
+fn state_path(program: &str, slot: u8) -> PathBuf {
+    let name = match program {
+        "-" => "stdin".into(),
+        p => Path::new(p).file_stem().map_or(p.into(), |s| s.to_string_lossy()),
+    };
+    PathBuf::from(format!("{}.{}.state", name, slot))
+}
+
+fn parse_speed(s: &str) -> u32 {
+    s.parse::<u32>().unwrap_or_else(|_| fail(format!("Invalid speed {}", s)))
+}
+
+fn parse_float(s: &str) -> f32 {
+    s.parse::<f32>().unwrap_or_else(|_| fail(format!("Invalid number {}", s)))
+}
+
+/// Reads the RPL flags saved by a previous run, a missing file means all zero.
+fn load_rpl(path: &str) -> [u8; 16] {
+    let mut flags = [0; 16];
+    if let Ok(mut f) = File::open(path) {
+        let mut b = Vec::new();
+        if f.read_to_end(&mut b).is_ok() {
+            let n = b.len().min(16);
+            flags[..n].copy_from_slice(&b[..n]);
+        }
+    }
+    flags
+}
+
+fn save_rpl(path: &str, flags: &[u8; 16]) {
+    let written = File::create(path).and_then(|mut f| f.write_all(flags));
+    if let Err(e) = written {
+        warn!("Unable to save RPL flags to {}: {}", path, e);
+    }
+}
+
+/// Builds the quirks from the --quirks profile and any --quirk overrides.
+fn quirks_from_args(matches: &clap::ArgMatches) -> Result<Quirks, String> {
+    let mut q = match matches.value_of("quirks") {
+        Some(name) => {
+            Quirks::from_profile(name)
+                .ok_or(format!("Unknown quirks profile {}, expected one of {}",
+                               name,
+                               quirks::PROFILES.join(", ")))?
+        }
+        None => Quirks::default(),
+    };
+
+    if let Some(overrides) = matches.values_of("quirk") {
+        for o in overrides {
+            q.apply_override(o)?;
+        }
+    }
+
+    Ok(q)
+}
+
+/// The config file first, then the command line on top of it. `--fg` and
+/// `--bg` win over the first two `--palette` colors.
+fn screen_config_from_args(matches: &clap::ArgMatches) -> Result<ScreenConfig, String> {
+    let mut config = ScreenConfig::default();
+    if let Some(path) = matches.value_of("config") {
+        config.load(path)?;
+    }
+
+    for name in &["scale", "palette", "bg", "fg"] {
+        if let Some(value) = matches.value_of(name) {
+            config.set(name, value)?;
+        }
+    }
+    if matches.is_present("fullscreen") {
+        config.fullscreen = true;
+    }
+
+    Ok(config)
+}
@@ -0,0 +1,162 @@
+use std::cmp;
+use std::fs;
+
+use sdl::video;
+use sdl::Rect;
+
+use chipate::display::{self, Display};
+use chipate::VideoSink;
+
+/// Settings a config file or the command line can change.
+pub static OPTIONS: [&str; 5] = ["scale", "fg", "bg", "palette", "fullscreen"];
+
+/// ScreenConfig
+/// Window size and colors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScreenConfig {
+    /// Window pixels per low resolution pixel, the starting window size.
+    pub scale: u16,
+    /// Pixel colors indexed by the plane bits, only the first two show up
+    /// outside XO-CHIP.
+    pub palette: [(u8, u8, u8); 4],
+    pub fullscreen: bool,
+}
+
+impl Default for ScreenConfig {
+    fn default() -> ScreenConfig {
+        ScreenConfig {
+            scale: 20,
+            palette: [(0, 0, 0), (255, 255, 255), (170, 170, 170), (85, 85, 85)],
+            fullscreen: false,
+        }
+    }
+}
+
+impl ScreenConfig {
+    /// Sets one of `OPTIONS` from its text form. Colors are `RRGGBB` in hex,
+    /// a palette is two or four of them separated by commas.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "scale" => {
+                self.scale = value.parse::<u16>()
+                    .ok()
+                    .filter(|s| (1..=100).contains(s))
+                    .ok_or_else(|| format!("Invalid scale {}, expected 1 to 100", value))?
+            }
+            "bg" => self.palette[0] = parse_color(value)?,
+            "fg" => self.palette[1] = parse_color(value)?,
+            "palette" => {
+                let colors = value.split(',').map(parse_color).collect::<Result<Vec<_>, _>>()?;
+                if colors.len() != 2 && colors.len() != 4 {
+                    return Err(format!("Palette {} needs 2 or 4 colors", value));
+                }
+                self.palette[..colors.len()].copy_from_slice(&colors);
+            }
+            "fullscreen" => {
+                self.fullscreen = match value.to_lowercase().as_str() {
+                    "on" | "true" | "1" => true,
+                    "off" | "false" | "0" => false,
+                    _ => return Err(format!("Invalid fullscreen {}, expected on or off", value)),
+                }
+            }
+            _ => return Err(format!("Unknown setting {}, expected one of {}", name, OPTIONS.join(", "))),
+        }
+        Ok(())
+    }
+
+    /// Applies a config file of `name = value` lines, lines starting with `#`
+    /// are comments.
+    pub fn load(&mut self, path: &str) -> Result<(), String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Unable to read {}: {}", path, e))?;
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut parts = line.splitn(2, '=');
+            let name = parts.next().unwrap().trim();
+            let value = parts.next()
+                .ok_or_else(|| format!("{} line {}: expected name = value", path, n + 1))?
+                .trim();
+            self.set(name, value).map_err(|e| format!("{} line {}: {}", path, n + 1, e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses `RRGGBB`, with or without a leading `#`.
+fn parse_color(s: &str) -> Result<(u8, u8, u8), String> {
+    let hex = s.trim().trim_start_matches('#');
+    let invalid = || format!("Invalid color {}, expected RRGGBB", s);
+    if hex.len() != 6 {
+        return Err(invalid());
+    }
+    let rgb = u32::from_str_radix(hex, 16).map_err(|_| invalid())?;
+    Ok(((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8))
+}
+
+/// Screen
+/// SDL window that renders a `Display` framebuffer. Pixels stay square at
+/// the largest whole size that fits the window, centered on the background.
+pub struct Screen {
+    surface: video::Surface,
+    config: ScreenConfig,
+}
+
+impl Screen {
+    pub fn new(config: ScreenConfig) -> Result<Screen, String> {
+        let surface = if config.fullscreen {
+            // 0x0 keeps the desktop resolution
+            set_mode(0, 0, video::VideoFlag::Fullscreen)?
+        } else {
+            set_mode(display::LORES_WIDTH as isize * config.scale as isize,
+                     display::LORES_HEIGHT as isize * config.scale as isize,
+                     video::VideoFlag::Resizable)?
+        };
+        Ok(Screen { surface, config })
+    }
+
+    /// Follows the window to its new size, render again afterwards.
+    pub fn resize(&mut self, w: isize, h: isize) {
+        if self.config.fullscreen {
+            return;
+        }
+        match set_mode(w, h, video::VideoFlag::Resizable) {
+            Ok(surface) => self.surface = surface,
+            Err(e) => warn!("Unable to resize the window to {}x{}: {}", w, h, e),
+        }
+    }
+}
+
+fn set_mode(w: isize, h: isize, flag: video::VideoFlag) -> Result<video::Surface, String> {
+    video::set_video_mode(w, h, 32, &[video::SurfaceFlag::HWSurface], &[video::VideoFlag::DoubleBuf, flag])
+}
+
+impl VideoSink for Screen {
+    fn render(&mut self, display: &Display) {
+        let (w, h) = (self.surface.get_width() as usize, self.surface.get_height() as usize);
+        let size = cmp::max(1, cmp::min(w / display.width(), h / display.height()));
+        let left = w.saturating_sub(size * display.width()) / 2;
+        let top = h.saturating_sub(size * display.height()) / 2;
+
+        // Both buffers of the double buffer need their margins cleared
+        let (r, g, b) = self.config.palette[0];
+        self.surface.fill_rect(None, video::RGB(r, g, b));
+
+        for (y, row) in display.gfx().iter().take(display.height()).enumerate() {
+            for (x, &p) in row.iter().take(display.width()).enumerate() {
+                let (r, g, b) = self.config.palette[(p & 0x3) as usize];
+                self.surface
+                    .fill_rect(Some(Rect {
+                                        x: (left + x * size) as i16,
+                                        y: (top + y * size) as i16,
+                                        w: size as u16,
+                                        h: size as u16,
+                                    }),
+                               video::RGB(r, g, b));
+            }
+        }
+
+        self.surface.flip();
+    }
+}
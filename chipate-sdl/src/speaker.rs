@@ -0,0 +1,84 @@
+use std::sync::Mutex;
+
+use sdl::audio;
+
+use chipate::audio::{ToneGenerator, Waveform};
+use chipate::AudioSink;
+
+pub static SAMPLE_RATE: u32 = 44100;
+
+lazy_static! {
+    // SDL calls back from its own thread with no way to pass state along
+    static ref TONE: Mutex<ToneGenerator> = Mutex::new(ToneGenerator::new(SAMPLE_RATE));
+}
+
+/// Speaker
+/// Plays the tone on the SDL audio device. Without a device it stays silent.
+pub struct Speaker {
+    open: bool,
+}
+
+impl Speaker {
+    pub fn new(frequency: f32, volume: f32, waveform: Waveform) -> Speaker {
+        {
+            let mut tone = TONE.lock().unwrap();
+            tone.set_frequency(frequency);
+            tone.set_volume(volume);
+            tone.set_waveform(waveform);
+        }
+
+        let desired = audio::DesiredAudioSpec {
+            freq: SAMPLE_RATE as i32,
+            format: audio::AudioFormat::S16LsbAudioFormat,
+            channels: audio::Channels::Mono,
+            samples: 1024,
+            callback: fill_buffer,
+        };
+
+        let open = match audio::open(desired) {
+            Ok(_) => {
+                audio::pause(false);
+                true
+            }
+            Err(_) => {
+                warn!("Unable to open the audio device, sound is off");
+                false
+            }
+        };
+
+        Speaker { open }
+    }
+}
+
+impl AudioSink for Speaker {
+    fn set_tone(&mut self, on: bool) {
+        if self.open {
+            TONE.lock().unwrap().set_tone(on);
+        }
+    }
+
+    fn set_pattern(&mut self, pattern: &[u8; 16], pitch: u8) {
+        if self.open {
+            TONE.lock().unwrap().set_pattern(pattern, pitch);
+        }
+    }
+}
+
+impl Drop for Speaker {
+    fn drop(&mut self) {
+        if self.open {
+            audio::close();
+        }
+    }
+}
+
+/// Audio callback, SDL hands over the buffer as bytes of little endian samples.
+fn fill_buffer(buf: &mut [u8]) {
+    let mut samples = vec![0i16; buf.len() / 2];
+    TONE.lock().unwrap().fill(&mut samples);
+
+    for (out, s) in buf.chunks_mut(2).zip(samples) {
+        out[0] = s as u8;
+        out[1] = (s >> 8) as u8;
+    }
+}
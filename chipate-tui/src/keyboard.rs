@@ -0,0 +1,62 @@
+use termion::event::Key;
+use termion::input::{Keys, TermRead};
+use termion::{async_stdin, AsyncReader};
+
+use chipate::keymap::Keymap;
+use chipate::InputSource;
+
+/// Terminals only report key presses, so a keypad key stays down for this
+/// many frames after its last press. Auto repeat keeps a held key down.
+static HOLD_FRAMES: u8 = 15;
+
+/// Keyboard
+/// Reads keys from stdin without blocking and turns them into keypad state
+/// through a `Keymap`. Escape or Ctrl-C quits.
+pub struct Keyboard {
+    keymap: Keymap,
+    input: Keys<AsyncReader>,
+    held: [u8; 16],
+    quit: bool,
+}
+
+impl Keyboard {
+    pub fn new(keymap: Keymap) -> Keyboard {
+        Keyboard {
+            keymap,
+            input: async_stdin().keys(),
+            held: [0; 16],
+            quit: false,
+        }
+    }
+
+    /// True once the user asked to close the emulator.
+    pub fn quit_requested(&self) -> bool {
+        self.quit
+    }
+}
+
+impl InputSource for Keyboard {
+    fn poll(&mut self, key: &mut [u8; 16]) {
+        for h in self.held.iter_mut() {
+            *h = h.saturating_sub(1);
+        }
+
+        for k in self.input.by_ref() {
+            match k {
+                Ok(Key::Esc) | Ok(Key::Ctrl('c')) => self.quit = true,
+                Ok(Key::Char(c)) => {
+                    if let Some(n) = self.keymap.keypad_key(c) {
+                        debug!("Key {:X} down", n);
+                        self.held[n] = HOLD_FRAMES;
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Unable to read the keyboard: {}", e),
+            }
+        }
+
+        for (k, &h) in key.iter_mut().zip(self.held.iter()) {
+            *k = (h > 0) as u8;
+        }
+    }
+}
@@ -0,0 +1,102 @@
+#[macro_use]
+extern crate log;
+extern crate env_logger;
+
+#[macro_use]
+extern crate clap;
+
+extern crate termion;
+
+extern crate chipate;
+
+#[path = "../../shared/cli.rs"]
+mod cli;
+mod keyboard;
+#[path = "../../shared/rom.rs"]
+mod rom;
+mod screen;
+
+use chipate::{Chipate, Quirks, Scheduler};
+use chipate::keymap::{self, Keymap};
+use chipate::rng;
+use cli::{fail, parse_address};
+use keyboard::Keyboard;
+use rom::load_rom;
+use screen::{Cells, Screen};
+
+fn main() {
+    env_logger::init().unwrap();
+    let matches = clap_app!(chipate_tui =>
+                            (version: "1.0")
+                            (author: "Robert J. Lambert III <rlambert85@gmail.com>")
+                            (about: "Runs Chip-8 programs in the terminal")
+                            (@arg program: -p --program +required +takes_value "Program to run: a ROM path or a name in ./programs")
+                            (@arg ipf: --ipf +takes_value "Instructions per 60 Hz frame (default 10)")
+                            (@arg hz: --hz +takes_value conflicts_with[ipf] "Instructions per second, instead of --ipf")
+                            (@arg quirks: --quirks +takes_value "Quirks profile: vip (default), chip48, schip or xochip")
+                            (@arg load_address: --("load-address") +takes_value "Where the ROM is loaded, 0x200 by default or 0x600 for ETI-660 ROMs")
+                            (@arg keymap: --keymap +takes_value "Keyboard keys for keypad keys 0 to F (default x123qweasdzc4rfv)")
+                            (@arg seed: --seed +takes_value "Seed for the random numbers, to repeat a run (default a fresh one)")
+                            (@arg braille: --braille "Draw with braille dots, smaller but without XO-CHIP colors")
+    )
+            .get_matches();
+
+    let program = matches.value_of("program").unwrap();
+
+    let mut scheduler = match (matches.value_of("ipf"), matches.value_of("hz")) {
+        (_, Some(hz)) => Scheduler::with_hz(parse_speed(hz)),
+        (Some(ipf), _) => Scheduler::with_ipf(parse_speed(ipf)),
+        (None, None) => Scheduler::with_ipf(10),
+    };
+
+    let mut chip = Chipate::new();
+    chip.init();
+    if let Some(name) = matches.value_of("quirks") {
+        let quirks = Quirks::from_profile(name).unwrap_or_else(|| fail(format!("Unknown quirks profile {}", name)));
+        chip.set_quirks(quirks);
+        chip.set_xochip(quirks == Quirks::xochip());
+    }
+    if let Some(addr) = matches.value_of("load_address") {
+        let addr = parse_address(addr).unwrap_or_else(|| fail(format!("Invalid load address {}", addr)));
+        chip.set_load_address(addr);
+    }
+
+    let seed = match matches.value_of("seed") {
+        Some(s) => s.parse::<u64>().unwrap_or_else(|_| fail(format!("Invalid seed {}", s))),
+        None => rng::random_seed(),
+    };
+    info!("Random seed {}", seed);
+    chip.set_seed(seed);
+
+    if let Err(e) = load_rom(&mut chip, program) {
+        fail(format!("Unable to load {}: {}", program, e));
+    }
+
+    let keymap = Keymap::from_layout(matches.value_of("keymap").unwrap_or(keymap::QWERTY))
+        .unwrap_or_else(|e| fail(e));
+    let cells = if matches.is_present("braille") { Cells::Braille } else { Cells::HalfBlock };
+
+    let result = {
+        let mut screen = Screen::new(cells).unwrap_or_else(|e| fail(format!("Unable to set up the terminal: {}", e)));
+        let mut keyboard = Keyboard::new(keymap);
+
+        let mut result = Ok(());
+        while !chip.exited() && !keyboard.quit_requested() && result.is_ok() {
+            chip.set_keys(&mut keyboard);
+            result = scheduler.run_frame(&mut chip);
+            chip.draw_screen(&mut screen);
+            chip.play_sound(&mut screen);
+            scheduler.wait();
+        }
+        // The terminal is back to normal once the screen is dropped
+        result
+    };
+
+    if let Err(e) = result {
+        fail(e);
+    }
+}
+
+fn parse_speed(s: &str) -> u32 {
+    s.parse::<u32>().unwrap_or_else(|_| fail(format!("Invalid speed {}", s)))
+}
@@ -0,0 +1,117 @@
+use std::fmt::Write as FmtWrite;
+use std::io::{self, Stdout, Write};
+
+use termion::clear;
+use termion::color::{self, Bg, Fg, Rgb};
+use termion::cursor;
+use termion::raw::{IntoRawMode, RawTerminal};
+
+use chipate::display::Display;
+use chipate::{AudioSink, VideoSink};
+
+// Pixel colors indexed by the plane bits, the same as the SDL window
+static PALETTE: [Rgb; 4] = [Rgb(0, 0, 0), Rgb(255, 255, 255), Rgb(170, 170, 170), Rgb(85, 85, 85)];
+
+/// Braille dot bits for each pixel of a 2x4 cell, indexed by [y][x].
+static DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// How the framebuffer is packed into characters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cells {
+    /// Upper half blocks, 1x2 pixels per character in full color.
+    HalfBlock,
+    /// Braille patterns, 2x4 pixels per character, every lit pixel in white.
+    Braille,
+}
+
+/// Screen
+/// Draws a `Display` on the terminal with ANSI colors. The terminal is put
+/// in raw mode for as long as the screen lives, so keys arrive one by one.
+pub struct Screen {
+    out: RawTerminal<Stdout>,
+    cells: Cells,
+    ringing: bool,
+}
+
+impl Screen {
+    pub fn new(cells: Cells) -> io::Result<Screen> {
+        let mut out = io::stdout().into_raw_mode()?;
+        write!(out, "{}{}", clear::All, cursor::Hide)?;
+        out.flush()?;
+        Ok(Screen { out, cells, ringing: false })
+    }
+
+    fn frame(&self, display: &Display) -> String {
+        let mut s = String::new();
+        let _ = write!(s, "{}", cursor::Goto(1, 1));
+        match self.cells {
+            Cells::HalfBlock => half_blocks(&mut s, display),
+            Cells::Braille => braille(&mut s, display),
+        }
+        s
+    }
+}
+
+impl VideoSink for Screen {
+    fn render(&mut self, display: &Display) {
+        let frame = self.frame(display);
+        if let Err(e) = self.out.write_all(frame.as_bytes()).and_then(|_| self.out.flush()) {
+            warn!("Unable to draw: {}", e);
+        }
+    }
+}
+
+/// Rings the terminal bell whenever the tone starts, there is no way to
+/// hold a note.
+impl AudioSink for Screen {
+    fn set_tone(&mut self, on: bool) {
+        if on && !self.ringing {
+            let _ = self.out.write_all(b"\x07").and_then(|_| self.out.flush());
+        }
+        self.ringing = on;
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = write!(self.out, "{}{}{}\r\n", Fg(color::Reset), Bg(color::Reset), cursor::Show);
+        let _ = self.out.flush();
+    }
+}
+
+/// Each character covers two rows, the top pixel in the foreground color
+/// and the bottom one in the background color. Colors are only sent when
+/// they change.
+fn half_blocks(s: &mut String, display: &Display) {
+    let mut last = None;
+    for rows in display.gfx()[..display.height()].chunks(2) {
+        for (&top, &bottom) in rows[0].iter().zip(rows[1].iter()).take(display.width()) {
+            let colors = (top & 0x3, bottom & 0x3);
+            if last != Some(colors) {
+                let _ = write!(s, "{}{}", Fg(PALETTE[colors.0 as usize]), Bg(PALETTE[colors.1 as usize]));
+                last = Some(colors);
+            }
+            s.push('\u{2580}');
+        }
+        s.push_str("\r\n");
+    }
+}
+
+fn braille(s: &mut String, display: &Display) {
+    let gfx = display.gfx();
+    let _ = write!(s, "{}{}", Fg(PALETTE[1]), Bg(PALETTE[0]));
+    for y in (0..display.height()).step_by(4) {
+        for x in (0..display.width()).step_by(2) {
+            let mut bits = 0;
+            for (dy, row) in DOTS.iter().enumerate() {
+                for (dx, &dot) in row.iter().enumerate() {
+                    if gfx[y + dy][x + dx] & 0x3 != 0 {
+                        bits |= dot;
+                    }
+                }
+            }
+            s.push(::std::char::from_u32(0x2800 + bits).unwrap());
+        }
+        s.push_str("\r\n");
+    }
+}
@@ -0,0 +1,35 @@
+//! Cli Module
+//! Argument helpers for the frontend binaries, which each include this file
+//! as their `cli` module so the core crate stays free of process exits.
+
+use std::fmt::Display;
+use std::process;
+
+/// Parses an address written in hex with a 0x prefix, or in decimal.
+pub fn parse_address(s: &str) -> Option<u16> {
+    if s.starts_with("0x") || s.starts_with("0X") {
+        u16::from_str_radix(&s[2..], 16).ok()
+    } else {
+        s.parse::<u16>().ok()
+    }
+}
+
+/// Reports a fatal error and quits.
+pub fn fail<E: Display>(e: E) -> ! {
+    eprintln!("Error: {}", e);
+    process::exit(1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn addresses_parse_in_hex_or_decimal() {
+        assert_eq!(parse_address("0x200"), Some(0x200));
+        assert_eq!(parse_address("0XFFFF"), Some(0xFFFF));
+        assert_eq!(parse_address("512"), Some(512));
+        assert_eq!(parse_address("0x10000"), None);
+        assert_eq!(parse_address("200h"), None);
+    }
+}
@@ -0,0 +1,24 @@
+//! Rom Module
+//! How the frontend binaries find the program to run, included by each as
+//! their `rom` module.
+
+use std::io::{self, Read};
+use std::path::Path;
+
+use chipate::{Chipate, EmuError};
+
+/// Loads the ROM from stdin for "-", from the path if there is such a file, or
+/// from the programs directory otherwise.
+pub fn load_rom(chip: &mut Chipate, program: &str) -> Result<(), EmuError> {
+    if program == "-" {
+        let mut b = Vec::new();
+        io::stdin().read_to_end(&mut b)?;
+        return chip.load_rom_from_bytes(&b);
+    }
+
+    if Path::new(program).is_file() {
+        chip.load_rom_from_path(program)
+    } else {
+        chip.load_program(program)
+    }
+}
@@ -0,0 +1,822 @@
+//! Assembler Module
+//! Assembles Octo source into a ROM: labels, `:const`, `:alias`, `:macro`,
+//! `:byte`, bare sprite bytes, `if ... then`, `if ... begin ... else ... end`
+//! and `loop ... while ... again`. Errors carry the source line.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+/// Where and why assembling failed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for AsmError {}
+
+/// Assembles `source` into a ROM loaded at 0x200.
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    assemble_at(source, 0x200)
+}
+
+/// Assembles `source` into a ROM loaded at `origin`.
+pub fn assemble_at(source: &str, origin: u16) -> Result<Vec<u8>, AsmError> {
+    let mut a = Assembler::new(tokenize(source), origin);
+    a.run()?;
+    Ok(a.rom)
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    text: String,
+    line: usize,
+}
+
+/// Splits the source into words, dropping `#` comments.
+fn tokenize(source: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    for (n, line) in source.lines().enumerate() {
+        let code = line.split('#').next().unwrap_or("");
+        for word in code.split_whitespace() {
+            tokens.push(Token {
+                text: word.to_string(),
+                line: n + 1,
+            });
+        }
+    }
+    tokens
+}
+
+/// Parses a decimal, 0x hex or 0b binary number, optionally negative.
+fn literal(s: &str) -> Option<i32> {
+    let (negative, digits) = if let Some(rest) = s.strip_prefix('-') { (true, rest) } else { (false, s) };
+    let n = if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        i32::from_str_radix(hex, 16).ok()?
+    } else if let Some(bin) = digits.strip_prefix("0b").or_else(|| digits.strip_prefix("0B")) {
+        i32::from_str_radix(bin, 2).ok()?
+    } else if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+        digits.parse().ok()?
+    } else {
+        return None;
+    };
+    Some(if negative { -n } else { n })
+}
+
+fn is_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+#[derive(Clone)]
+struct Macro {
+    params: Vec<String>,
+    body: Vec<Token>,
+}
+
+/// Open `if ... begin`, `else` and `loop` blocks, with the offsets of the
+/// jumps waiting for the end of the block.
+enum Block {
+    If { jump: usize, line: usize },
+    Else { jump: usize, line: usize },
+    Loop { start: u16, breaks: Vec<usize>, line: usize },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Field {
+    // Low 12 bits of an opcode
+    Nnn,
+    // The word after F000
+    Long,
+}
+
+/// A label used before it was defined, patched in at the end.
+struct Fixup {
+    at: usize,
+    label: String,
+    field: Field,
+    line: usize,
+}
+
+/// Right hand side of a condition.
+enum Operand {
+    Value(u8),
+    Register(u8),
+    Key,
+}
+
+struct Condition {
+    x: u8,
+    operand: Operand,
+    equal: bool,
+}
+
+impl Condition {
+    /// The skip opcode that lets the next instruction run only when the condition holds.
+    fn then_opcode(&self) -> u16 {
+        let x = (self.x as u16) << 8;
+        match (&self.operand, self.equal) {
+            (&Operand::Value(n), true) => 0x4000 | x | n as u16,
+            (&Operand::Value(n), false) => 0x3000 | x | n as u16,
+            (&Operand::Register(y), true) => 0x9000 | x | (y as u16) << 4,
+            (&Operand::Register(y), false) => 0x5000 | x | (y as u16) << 4,
+            (&Operand::Key, true) => 0xE0A1 | x,
+            (&Operand::Key, false) => 0xE09E | x,
+        }
+    }
+
+    fn negate(self) -> Condition {
+        Condition { equal: !self.equal, ..self }
+    }
+}
+
+// Guards against macros that expand into themselves
+const MAX_EXPANSIONS: usize = 10000;
+
+struct Assembler {
+    tokens: Vec<Token>,
+    pos: usize,
+
+    // Line of the token read last, for error messages
+    line: usize,
+
+    origin: u16,
+    rom: Vec<u8>,
+
+    labels: HashMap<String, u16>,
+    consts: HashMap<String, i32>,
+    aliases: HashMap<String, u8>,
+    macros: HashMap<String, Macro>,
+    expansions: usize,
+
+    fixups: Vec<Fixup>,
+    blocks: Vec<Block>,
+}
+
+impl Assembler {
+    fn new(tokens: Vec<Token>, origin: u16) -> Assembler {
+        Assembler {
+            tokens,
+            pos: 0,
+            line: 1,
+            origin,
+            rom: Vec::new(),
+            labels: HashMap::new(),
+            consts: HashMap::new(),
+            aliases: HashMap::new(),
+            macros: HashMap::new(),
+            expansions: 0,
+            fixups: Vec::new(),
+            blocks: Vec::new(),
+        }
+    }
+
+    fn err<T, S: Into<String>>(&self, message: S) -> Result<T, AsmError> {
+        Err(AsmError {
+            line: self.line,
+            message: message.into(),
+        })
+    }
+
+    fn next(&mut self) -> Result<String, AsmError> {
+        match self.tokens.get(self.pos) {
+            Some(t) => {
+                self.pos += 1;
+                self.line = t.line;
+                Ok(t.text.clone())
+            }
+            None => self.err("Unexpected end of file"),
+        }
+    }
+
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(|t| t.text.as_str())
+    }
+
+    fn expect(&mut self, word: &str) -> Result<(), AsmError> {
+        let t = self.next()?;
+        if t != word {
+            return self.err(format!("Expected {}, found {}", word, t));
+        }
+        Ok(())
+    }
+
+    fn here(&self) -> u16 {
+        self.origin.wrapping_add(self.rom.len() as u16)
+    }
+
+    fn emit_byte(&mut self, b: u8) -> Result<(), AsmError> {
+        if self.origin as usize + self.rom.len() > 0xFFFF {
+            return self.err("Program does not fit in memory");
+        }
+        self.rom.push(b);
+        Ok(())
+    }
+
+    fn emit(&mut self, opcode: u16) -> Result<(), AsmError> {
+        self.emit_byte((opcode >> 8) as u8)?;
+        self.emit_byte(opcode as u8)
+    }
+
+    fn run(&mut self) -> Result<(), AsmError> {
+        // Execution starts with a jump to main, dropped again when main comes first
+        self.address_of("main", Field::Nnn);
+        self.emit(0x1000)?;
+
+        while self.pos < self.tokens.len() {
+            let word = self.next()?;
+            self.statement(&word)?;
+        }
+
+        match self.blocks.last() {
+            Some(&Block::Loop { line, .. }) => {
+                self.line = line;
+                return self.err("loop without again");
+            }
+            Some(&Block::If { line, .. }) | Some(&Block::Else { line, .. }) => {
+                self.line = line;
+                return self.err("begin without end");
+            }
+            None => {}
+        }
+        if !self.labels.contains_key("main") {
+            return self.err("The program has no main label");
+        }
+
+        for f in &self.fixups {
+            let addr = match self.labels.get(&f.label) {
+                Some(&addr) => addr,
+                None => {
+                    return Err(AsmError {
+                        line: f.line,
+                        message: format!("Undefined label {}", f.label),
+                    })
+                }
+            };
+            if f.field == Field::Nnn && addr > 0xFFF {
+                return Err(AsmError {
+                    line: f.line,
+                    message: format!("{} at 0x{:X} is out of reach, use i := long", f.label, addr),
+                });
+            }
+            patch(&mut self.rom, f.at, f.field, addr);
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, word: &str) -> Result<(), AsmError> {
+        match word {
+            ":" => {
+                let name = self.next()?;
+                self.define_label(name)
+            }
+            ":const" => {
+                let name = self.next()?;
+                let value = self.value(-0x8000, 0xFFFF)?;
+                self.check_name(&name)?;
+                self.consts.insert(name, value);
+                Ok(())
+            }
+            ":alias" => {
+                let name = self.next()?;
+                let x = self.register()?;
+                self.check_name(&name)?;
+                self.aliases.insert(name, x);
+                Ok(())
+            }
+            ":macro" => self.define_macro(),
+            ":byte" => {
+                let b = self.value(-128, 255)?;
+                self.emit_byte(b as u8)
+            }
+            ":call" => self.jump(0x2000),
+            "jump" => self.jump(0x1000),
+            "jump0" => self.jump(0xB000),
+            "return" | ";" => self.emit(0x00EE),
+            "clear" => self.emit(0x00E0),
+            "exit" => self.emit(0x00FD),
+            "lores" => self.emit(0x00FE),
+            "hires" => self.emit(0x00FF),
+            "scroll-right" => self.emit(0x00FB),
+            "scroll-left" => self.emit(0x00FC),
+            "scroll-down" => {
+                let n = self.value(0, 15)? as u16;
+                self.emit(0x00C0 | n)
+            }
+            "scroll-up" => {
+                let n = self.value(0, 15)? as u16;
+                self.emit(0x00D0 | n)
+            }
+            "plane" => {
+                let n = self.value(0, 15)? as u16;
+                self.emit(0xF001 | n << 8)
+            }
+            "audio" => self.emit(0xF002),
+            "sprite" => {
+                let x = self.register()? as u16;
+                let y = self.register()? as u16;
+                let n = self.value(0, 15)? as u16;
+                self.emit(0xD000 | x << 8 | y << 4 | n)
+            }
+            "bcd" => self.register_op(0xF033),
+            "saveflags" => self.register_op(0xF075),
+            "loadflags" => self.register_op(0xF085),
+            "save" | "load" => {
+                let x = self.register()? as u16;
+                if self.peek() == Some("-") {
+                    self.next()?;
+                    let y = self.register()? as u16;
+                    let op = if word == "save" { 0x5002 } else { 0x5003 };
+                    self.emit(op | x << 8 | y << 4)
+                } else {
+                    let op = if word == "save" { 0xF055 } else { 0xF065 };
+                    self.emit(op | x << 8)
+                }
+            }
+            "i" => self.i_statement(),
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let op = match word {
+                    "delay" => 0xF015,
+                    "buzzer" => 0xF018,
+                    _ => 0xF03A,
+                };
+                self.register_op(op)
+            }
+            "if" => self.if_statement(),
+            "else" => {
+                let line = self.line;
+                let jump = match self.blocks.pop() {
+                    Some(Block::If { jump, .. }) => jump,
+                    _ => return self.err("else without if ... begin"),
+                };
+                let skip = self.rom.len();
+                self.emit(0x1000)?;
+                let here = self.target("The else block", self.here())?;
+                patch(&mut self.rom, jump, Field::Nnn, here);
+                self.blocks.push(Block::Else { jump: skip, line });
+                Ok(())
+            }
+            "end" => {
+                let jump = match self.blocks.pop() {
+                    Some(Block::If { jump, .. }) | Some(Block::Else { jump, .. }) => jump,
+                    _ => return self.err("end without if ... begin"),
+                };
+                let here = self.target("The end of the block", self.here())?;
+                patch(&mut self.rom, jump, Field::Nnn, here);
+                Ok(())
+            }
+            "loop" => {
+                let start = self.here();
+                let line = self.line;
+                self.blocks.push(Block::Loop {
+                    start,
+                    breaks: Vec::new(),
+                    line,
+                });
+                Ok(())
+            }
+            "while" => {
+                let c = self.condition()?;
+                self.emit(c.negate().then_opcode())?;
+                let jump = self.rom.len();
+                match self.blocks.iter_mut().rev().find(|b| matches!(b, Block::Loop { .. })) {
+                    Some(&mut Block::Loop { ref mut breaks, .. }) => breaks.push(jump),
+                    _ => return self.err("while outside of a loop"),
+                }
+                self.emit(0x1000)
+            }
+            "again" => {
+                let (start, breaks) = match self.blocks.pop() {
+                    Some(Block::Loop { start, breaks, .. }) => (start, breaks),
+                    _ => return self.err("again without loop"),
+                };
+                let start = self.target("The loop", start)?;
+                self.emit(0x1000 | start)?;
+                let here = self.here();
+                if !breaks.is_empty() {
+                    self.target("The end of the loop", here)?;
+                }
+                for b in breaks {
+                    patch(&mut self.rom, b, Field::Nnn, here);
+                }
+                Ok(())
+            }
+            _ => self.other(word),
+        }
+    }
+
+    /// Register operations, sprite bytes, macro uses and calls.
+    fn other(&mut self, word: &str) -> Result<(), AsmError> {
+        if let Some(x) = self.reg_of(word) {
+            return self.register_statement(x);
+        }
+        if let Some(m) = self.macros.get(word).cloned() {
+            return self.expand(m);
+        }
+        if literal(word).is_some() || self.consts.contains_key(word) {
+            let b = self.resolve(word, -128, 255)?;
+            return self.emit_byte(b as u8);
+        }
+        if word.starts_with(':') || !is_name(word) {
+            return self.err(format!("Unexpected {}", word));
+        }
+
+        // A bare name calls the subroutine of that name
+        let nnn = self.address_of(word, Field::Nnn);
+        self.emit(0x2000 | nnn)
+    }
+
+    fn define_label(&mut self, name: String) -> Result<(), AsmError> {
+        self.check_name(&name)?;
+        if self.labels.contains_key(&name) {
+            return self.err(format!("Label {} is defined twice", name));
+        }
+
+        if name == "main" && self.rom.len() == 2 && self.labels.is_empty() {
+            // Nothing before main, so the jump to it is not needed
+            self.rom.clear();
+            self.fixups.retain(|f| f.label != "main");
+        }
+
+        let here = self.here();
+        self.labels.insert(name, here);
+        Ok(())
+    }
+
+    fn check_name(&self, name: &str) -> Result<(), AsmError> {
+        if !is_name(name) || self.reg_of(name).is_some() {
+            return self.err(format!("{} can not be used as a name", name));
+        }
+        Ok(())
+    }
+
+    fn define_macro(&mut self) -> Result<(), AsmError> {
+        let name = self.next()?;
+        self.check_name(&name)?;
+
+        let mut params = Vec::new();
+        loop {
+            let t = self.next()?;
+            if t == "{" {
+                break;
+            }
+            params.push(t);
+        }
+
+        let mut body = Vec::new();
+        let mut depth = 1;
+        loop {
+            let t = self.next()?;
+            match t.as_str() {
+                "{" => depth += 1,
+                "}" => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                break;
+            }
+            body.push(Token { text: t, line: self.line });
+        }
+
+        self.macros.insert(name, Macro { params, body });
+        Ok(())
+    }
+
+    /// Replaces the macro use with its body, arguments substituted.
+    fn expand(&mut self, m: Macro) -> Result<(), AsmError> {
+        self.expansions += 1;
+        if self.expansions > MAX_EXPANSIONS {
+            return self.err("Macros expand too deep, does one use itself?");
+        }
+
+        let mut args = HashMap::new();
+        for p in &m.params {
+            let line = self.line;
+            let arg = self.next()?;
+            args.insert(p.clone(), Token { text: arg, line });
+        }
+
+        let body: Vec<Token> = m.body
+            .iter()
+            .map(|t| args.get(&t.text).cloned().unwrap_or_else(|| t.clone()))
+            .collect();
+        let at = self.pos;
+        self.tokens.splice(at..at, body);
+        Ok(())
+    }
+
+    fn reg_of(&self, s: &str) -> Option<u8> {
+        if let Some(&x) = self.aliases.get(s) {
+            return Some(x);
+        }
+        let mut chars = s.chars();
+        match (chars.next(), chars.next(), chars.next()) {
+            (Some('v'), Some(d), None) | (Some('V'), Some(d), None) => d.to_digit(16).map(|d| d as u8),
+            _ => None,
+        }
+    }
+
+    /// `addr` as the target of a block's jump, which only reaches 12 bits.
+    fn target(&self, what: &str, addr: u16) -> Result<u16, AsmError> {
+        if addr > 0xFFF {
+            return self.err(format!("{} at 0x{:X} is out of reach of its jump", what, addr));
+        }
+        Ok(addr)
+    }
+
+        fn register(&mut self) -> Result<u8, AsmError> {
+        let t = self.next()?;
+        match self.reg_of(&t) {
+            Some(x) => Ok(x),
+            None => self.err(format!("Expected a register, found {}", t)),
+        }
+    }
+
+    fn register_op(&mut self, opcode: u16) -> Result<(), AsmError> {
+        let x = self.register()? as u16;
+        self.emit(opcode | x << 8)
+    }
+
+    /// A number, constant or already defined label between `min` and `max`.
+    fn resolve(&self, word: &str, min: i32, max: i32) -> Result<i32, AsmError> {
+        let n = literal(word)
+            .or_else(|| self.consts.get(word).cloned())
+            .or_else(|| self.labels.get(word).map(|&a| a as i32));
+        match n {
+            Some(n) if n >= min && n <= max => Ok(n),
+            Some(n) => self.err(format!("{} ({}) is out of range {} to {}", word, n, min, max)),
+            None => self.err(format!("Expected a number, found {}", word)),
+        }
+    }
+
+    fn value(&mut self, min: i32, max: i32) -> Result<i32, AsmError> {
+        let t = self.next()?;
+        self.resolve(&t, min, max)
+    }
+
+    /// The address of `label`, or 0 with a fixup for the instruction about to
+    /// be emitted when it is not defined yet.
+    fn address_of(&mut self, label: &str, field: Field) -> u16 {
+        if let Some(&addr) = self.labels.get(label) {
+            if field == Field::Long || addr <= 0xFFF {
+                return addr;
+            }
+        }
+        let at = self.rom.len() + if field == Field::Long { 2 } else { 0 };
+        self.fixups.push(Fixup {
+            at,
+            label: label.to_string(),
+            field,
+            line: self.line,
+        });
+        0
+    }
+
+    fn address(&mut self, field: Field) -> Result<u16, AsmError> {
+        let t = self.next()?;
+        if literal(&t).is_some() || self.consts.contains_key(&t) {
+            let max = if field == Field::Long { 0xFFFF } else { 0xFFF };
+            return Ok(self.resolve(&t, 0, max)? as u16);
+        }
+        if !is_name(&t) {
+            return self.err(format!("Expected an address, found {}", t));
+        }
+        Ok(self.address_of(&t, field))
+    }
+
+    fn jump(&mut self, opcode: u16) -> Result<(), AsmError> {
+        let nnn = self.address(Field::Nnn)?;
+        self.emit(opcode | nnn)
+    }
+
+    fn i_statement(&mut self) -> Result<(), AsmError> {
+        let op = self.next()?;
+        match op.as_str() {
+            ":=" => match self.peek() {
+                Some("hex") => {
+                    self.next()?;
+                    self.register_op(0xF029)
+                }
+                Some("bighex") => {
+                    self.next()?;
+                    self.register_op(0xF030)
+                }
+                Some("long") => {
+                    self.next()?;
+                    let addr = self.address(Field::Long)?;
+                    self.emit(0xF000)?;
+                    self.emit(addr)
+                }
+                _ => {
+                    let nnn = self.address(Field::Nnn)?;
+                    self.emit(0xA000 | nnn)
+                }
+            },
+            "+=" => self.register_op(0xF01E),
+            _ => self.err(format!("Unknown operation i {}", op)),
+        }
+    }
+
+    fn register_statement(&mut self, x: u8) -> Result<(), AsmError> {
+        let x16 = (x as u16) << 8;
+        let op = self.next()?;
+        let rhs = self.peek().and_then(|t| self.reg_of(t));
+
+        let alu = |n: u16| -> Option<u16> { rhs.map(|y| 0x8000 | x16 | (y as u16) << 4 | n) };
+        let opcode = match op.as_str() {
+            ":=" => match self.peek() {
+                _ if rhs.is_some() => alu(0x0),
+                Some("random") => {
+                    self.next()?;
+                    let n = self.value(0, 255)? as u16;
+                    return self.emit(0xC000 | x16 | n);
+                }
+                Some("delay") => {
+                    self.next()?;
+                    return self.emit(0xF007 | x16);
+                }
+                Some("key") => {
+                    self.next()?;
+                    return self.emit(0xF00A | x16);
+                }
+                _ => {
+                    let n = self.value(-128, 255)? as u8;
+                    return self.emit(0x6000 | x16 | n as u16);
+                }
+            },
+            "+=" if rhs.is_none() => {
+                let n = self.value(-128, 255)? as u8;
+                return self.emit(0x7000 | x16 | n as u16);
+            }
+            "-=" if rhs.is_none() => {
+                let n = self.value(-128, 255)? as u8;
+                return self.emit(0x7000 | x16 | n.wrapping_neg() as u16);
+            }
+            "|=" => alu(0x1),
+            "&=" => alu(0x2),
+            "^=" => alu(0x3),
+            "+=" => alu(0x4),
+            "-=" => alu(0x5),
+            ">>=" => alu(0x6),
+            "=-" => alu(0x7),
+            "<<=" => alu(0xE),
+            _ => return self.err(format!("Unknown operation v{:x} {}", x, op)),
+        };
+
+        match opcode {
+            Some(opcode) => {
+                self.next()?;
+                self.emit(opcode)
+            }
+            None => {
+                let t = self.next()?;
+                self.err(format!("Expected a register after {}, found {}", op, t))
+            }
+        }
+    }
+
+    fn condition(&mut self) -> Result<Condition, AsmError> {
+        let x = self.register()?;
+        let op = self.next()?;
+        let (operand, equal) = match op.as_str() {
+            "key" => (Operand::Key, true),
+            "-key" => (Operand::Key, false),
+            "==" | "!=" => {
+                let t = self.next()?;
+                let operand = match self.reg_of(&t) {
+                    Some(y) => Operand::Register(y),
+                    None => Operand::Value(self.resolve(&t, -128, 255)? as u8),
+                };
+                (operand, op == "==")
+            }
+            _ => return self.err(format!("Unsupported comparison {}, use ==, !=, key or -key", op)),
+        };
+        Ok(Condition { x, operand, equal })
+    }
+
+    fn if_statement(&mut self) -> Result<(), AsmError> {
+        let c = self.condition()?;
+        let line = self.line;
+        match self.next()?.as_str() {
+            "then" => self.emit(c.then_opcode()),
+            "begin" => {
+                // Skip the jump past the block when the condition holds
+                self.emit(c.negate().then_opcode())?;
+                let jump = self.rom.len();
+                self.emit(0x1000)?;
+                self.blocks.push(Block::If { jump, line });
+                Ok(())
+            }
+            t => self.err(format!("Expected then or begin, found {}", t)),
+        }
+    }
+}
+
+/// Writes `addr` into the field at offset `at`.
+fn patch(rom: &mut [u8], at: usize, field: Field, addr: u16) {
+    match field {
+        Field::Nnn => {
+            rom[at] = rom[at] & 0xF0 | (addr >> 8) as u8 & 0x0F;
+            rom[at + 1] = addr as u8;
+        }
+        Field::Long => {
+            rom[at] = (addr >> 8) as u8;
+            rom[at + 1] = addr as u8;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(source: &str) -> AsmError {
+        assemble(source).unwrap_err()
+    }
+
+    #[test]
+    fn main_first_drops_the_leading_jump() {
+        assert_eq!(assemble(": main v0 := 5").unwrap(), [0x60, 0x05]);
+        assert_eq!(assemble(": sub return : main sub").unwrap(), [0x12, 0x04, 0x00, 0xEE, 0x22, 0x02]);
+    }
+
+    #[test]
+    fn forward_references_are_fixed_up() {
+        assert_eq!(assemble(": main jump later v1 := 1 : later v0 := 2").unwrap(),
+                   [0x12, 0x04, 0x61, 0x01, 0x60, 0x02]);
+        assert_eq!(assemble(": main :call sub i := data : sub return : data 0xAB").unwrap(),
+                   [0x22, 0x04, 0xA2, 0x06, 0x00, 0xEE, 0xAB]);
+        assert_eq!(assemble(": main i := long data : data 0xAB").unwrap(), [0xF0, 0x00, 0x02, 0x04, 0xAB]);
+    }
+
+    #[test]
+    fn consts_aliases_and_macros() {
+        assert_eq!(assemble(":const SPEED 3 :alias x v4 : main x := SPEED x += SPEED").unwrap(),
+                   [0x64, 0x03, 0x74, 0x03]);
+        assert_eq!(assemble(":macro set reg n { reg := n } : main set v2 7 set v3 8").unwrap(),
+                   [0x62, 0x07, 0x63, 0x08]);
+    }
+
+    #[test]
+    fn if_blocks() {
+        assert_eq!(assemble(": main if v0 == 5 then v1 := 1").unwrap(), [0x40, 0x05, 0x61, 0x01]);
+        assert_eq!(assemble(": main if v0 == 1 begin v1 := 1 else v1 := 2 end").unwrap(),
+                   [0x30, 0x01, 0x12, 0x08, 0x61, 0x01, 0x12, 0x0A, 0x61, 0x02]);
+        assert_eq!(assemble(": main if v0 != 1 begin v1 := 1 end").unwrap(), [0x40, 0x01, 0x12, 0x06, 0x61, 0x01]);
+    }
+
+    #[test]
+    fn loops() {
+        assert_eq!(assemble(": main loop v0 += 1 while v0 != 3 again").unwrap(),
+                   [0x70, 0x01, 0x40, 0x03, 0x12, 0x08, 0x12, 0x00]);
+        assert_eq!(assemble(": main v0 := 0 loop again").unwrap(), [0x60, 0x00, 0x12, 0x02]);
+    }
+
+    /// `code` on line 3, placed at `at` behind a jump and padding.
+    fn placed_at(at: usize, code: &str) -> String {
+        format!(": main jump code\n{}\n: code {}", ":byte 0 ".repeat(at - 0x202), code)
+    }
+
+    #[test]
+    fn block_jumps_past_0xfff_are_errors() {
+        let cases = [(0x1000, "loop v0 += 1 again", "The loop at 0x1000"),
+                     (0xFF8, "loop v0 += 1 while v0 != 3 again", "The end of the loop at 0x1000"),
+                     (0xFF8, "if v0 == 1 begin v1 := 1 else v1 := 2 end", "The else block at 0x1000"),
+                     (0xFFA, "if v0 == 1 begin v1 := 1 end", "The end of the block at 0x1000")];
+        for &(at, code, what) in &cases {
+            let e = error(&placed_at(at, code));
+            assert_eq!(e.line, 3, "{}", code);
+            assert_eq!(e.message, format!("{} is out of reach of its jump", what));
+        }
+        assert!(assemble(&placed_at(0xFFE, "loop again")).is_ok());
+    }
+
+    #[test]
+    fn errors_carry_the_line() {
+        assert_eq!(error(": main\n  v0 := 1\n  jump nowhere\n"),
+                   AsmError { line: 3, message: "Undefined label nowhere".to_string() });
+        assert_eq!(error(": main\n  loop\n    v0 += 1\n"),
+                   AsmError { line: 2, message: "loop without again".to_string() });
+        assert_eq!(error(": main\n\n  if v0 == 1 begin\n  v1 := 2\n"),
+                   AsmError { line: 3, message: "begin without end".to_string() });
+        assert_eq!(error(": main\n  v0 := 1\n  else\n"),
+                   AsmError { line: 3, message: "else without if ... begin".to_string() });
+        assert_eq!(error("# main\n: main\n: main\n"),
+                   AsmError { line: 3, message: "Label main is defined twice".to_string() });
+        assert_eq!(error(": main\n  v0 := vz\n").line, 2);
+        assert_eq!(error(": start\n  return\n").message, "The program has no main label");
+        assert_eq!(error(": main\n  jump nowhere").to_string(), "line 2: Undefined label nowhere");
+    }
+}
@@ -0,0 +1,218 @@
+//! Audio Module
+//! Sample generation for the sound timer, independent of any audio device.
+//! `ToneGenerator` produces the samples, `WavSink` renders them to a WAV
+//! file so the sound can be checked without speakers.
+
+use std::f32::consts::PI;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use frontend::AudioSink;
+use scheduler::FRAME_RATE;
+
+/// Shape of the tone played while the sound timer runs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Waveform {
+    Square,
+    Triangle,
+    Sawtooth,
+    Sine,
+}
+
+impl Waveform {
+    pub fn from_name(name: &str) -> Option<Waveform> {
+        match name.to_lowercase().as_str() {
+            "square" => Some(Waveform::Square),
+            "triangle" => Some(Waveform::Triangle),
+            "sawtooth" | "saw" => Some(Waveform::Sawtooth),
+            "sine" => Some(Waveform::Sine),
+            _ => None,
+        }
+    }
+
+    /// Level between -1 and 1 at `phase`, a fraction of one period.
+    fn level(&self, phase: f32) -> f32 {
+        match *self {
+            Waveform::Square => if phase < 0.5 { 1.0 } else { -1.0 },
+            Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+            Waveform::Sawtooth => 2.0 * phase - 1.0,
+            Waveform::Sine => (2.0 * PI * phase).sin(),
+        }
+    }
+}
+
+/// Generates 16 bit mono samples for the tone, or for the XO-CHIP pattern
+/// buffer once a program has set one.
+pub struct ToneGenerator {
+    sample_rate: u32,
+    frequency: f32,
+    volume: f32,
+    waveform: Waveform,
+    on: bool,
+
+    // Position within the current period, 0 to 1
+    phase: f32,
+
+    // XO-CHIP pattern, its playback rate in bits per second and the bit playing
+    pattern: Option<[u8; 16]>,
+    pattern_rate: f32,
+    pattern_pos: f32,
+}
+
+impl ToneGenerator {
+    /// A 440 Hz square wave at a quarter volume.
+    pub fn new(sample_rate: u32) -> ToneGenerator {
+        ToneGenerator {
+            sample_rate,
+            frequency: 440.0,
+            volume: 0.25,
+            waveform: Waveform::Square,
+            on: false,
+            phase: 0.0,
+            pattern: None,
+            pattern_rate: 4000.0,
+            pattern_pos: 0.0,
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn set_frequency(&mut self, hz: f32) {
+        self.frequency = hz;
+    }
+
+    /// Volume from 0 (silent) to 1 (full scale), clamped.
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, 1.0);
+    }
+
+    pub fn set_waveform(&mut self, waveform: Waveform) {
+        self.waveform = waveform;
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.on
+    }
+
+    fn next_sample(&mut self) -> i16 {
+        if !self.on {
+            return 0;
+        }
+
+        let level = match self.pattern {
+            Some(ref pattern) => {
+                let bit = self.pattern_pos as usize % 128;
+                self.pattern_pos = (self.pattern_pos + self.pattern_rate / self.sample_rate as f32) % 128.0;
+                if pattern[bit / 8] & (0x80 >> (bit % 8)) != 0 { 1.0 } else { -1.0 }
+            }
+            None => {
+                let level = self.waveform.level(self.phase);
+                self.phase = (self.phase + self.frequency / self.sample_rate as f32) % 1.0;
+                level
+            }
+        };
+
+        (level * self.volume * i16::MAX as f32) as i16
+    }
+
+    /// Fills `out` with the next samples, silence while the tone is off.
+    pub fn fill(&mut self, out: &mut [i16]) {
+        for s in out.iter_mut() {
+            *s = self.next_sample();
+        }
+    }
+}
+
+impl AudioSink for ToneGenerator {
+    fn set_tone(&mut self, on: bool) {
+        if on && !self.on {
+            self.phase = 0.0;
+            self.pattern_pos = 0.0;
+        }
+        self.on = on;
+    }
+
+    fn set_pattern(&mut self, pattern: &[u8; 16], pitch: u8) {
+        self.pattern = Some(*pattern);
+        self.pattern_rate = 4000.0 * 2f32.powf((pitch as f32 - 64.0) / 48.0);
+    }
+}
+
+/// Records the tone to memory and writes it out as a WAV file.
+/// Every `set_tone` call renders one 60 Hz frame, so hand it to
+/// `Chipate::play_sound` once per frame.
+pub struct WavSink {
+    tone: ToneGenerator,
+    samples: Vec<i16>,
+}
+
+impl WavSink {
+    pub fn new(tone: ToneGenerator) -> WavSink {
+        WavSink {
+            tone,
+            samples: Vec::new(),
+        }
+    }
+
+    /// Everything recorded so far.
+    pub fn samples(&self) -> &[i16] {
+        &self.samples
+    }
+
+    /// Writes a 16 bit mono PCM WAV file.
+    pub fn write<W: Write>(&self, mut w: W) -> io::Result<()> {
+        let rate = self.tone.sample_rate();
+        let data_len = self.samples.len() as u32 * 2;
+
+        w.write_all(b"RIFF")?;
+        w.write_all(&le32(36 + data_len))?;
+        w.write_all(b"WAVE")?;
+
+        w.write_all(b"fmt ")?;
+        w.write_all(&le32(16))?;
+        w.write_all(&le16(1))?; // PCM
+        w.write_all(&le16(1))?; // Mono
+        w.write_all(&le32(rate))?;
+        w.write_all(&le32(rate * 2))?; // Bytes per second
+        w.write_all(&le16(2))?; // Bytes per sample
+        w.write_all(&le16(16))?; // Bits per sample
+
+        w.write_all(b"data")?;
+        w.write_all(&le32(data_len))?;
+        for &s in &self.samples {
+            w.write_all(&le16(s as u16))?;
+        }
+        Ok(())
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let f = File::create(path)?;
+        self.write(BufWriter::new(f))
+    }
+}
+
+impl AudioSink for WavSink {
+    fn set_tone(&mut self, on: bool) {
+        self.tone.set_tone(on);
+
+        let start = self.samples.len();
+        let frame = (self.tone.sample_rate() / FRAME_RATE) as usize;
+        self.samples.resize(start + frame, 0);
+        self.tone.fill(&mut self.samples[start..]);
+    }
+
+    fn set_pattern(&mut self, pattern: &[u8; 16], pitch: u8) {
+        self.tone.set_pattern(pattern, pitch);
+    }
+}
+
+fn le16(v: u16) -> [u8; 2] {
+    [v as u8, (v >> 8) as u8]
+}
+
+fn le32(v: u32) -> [u8; 4] {
+    [v as u8, (v >> 8) as u8, (v >> 16) as u8, (v >> 24) as u8]
+}
@@ -1,9 +1,17 @@
-use std::{thread, time};
+use std::collections::VecDeque;
 use std::env;
 use std::io::prelude::*;
+use std::mem;
 use std::fs::File;
+use std::path::Path;
+
 
 use display::Display;
+use error::EmuError;
+use frontend::{AudioSink, InputSource, VideoSink};
+use quirks::Quirks;
+use rng::{self, Rng, SeededRng};
+use state::State;
 
 /// Chipate Module
 /// Rust emulation of the Chip-8
@@ -13,12 +21,13 @@ pub struct Chipate<'c> {
     // Opcodes
     opcode: u16,
 
-    // 4K Memory
-    memory: [u8; 4096],
+    // 4K Memory, 64K in XO-CHIP mode
+    memory: Vec<u8>,
     // The systems memory map:
     // 0x000-0x1FF - Chip 8 interpreter (contains font set in emu)
-    // 0x050-0x0A0 - Used for the built in 4x5 pixel font set (0-F)
-    // 0x200-0xFFF - Program ROM and work RAM
+    // 0x000-0x04F - Used for the built in 4x5 pixel font set (0-F)
+    // 0x050-0x0EF - Used for the SUPER-CHIP 8x10 pixel font set (0-F)
+    // 0x200-0xFFF - Program ROM and work RAM (up to 0xFFFF for XO-CHIP)
 
     // Registers
     v: [u8; 16],
@@ -43,18 +52,47 @@ pub struct Chipate<'c> {
     // Keypad
     key: [u8; 16],
 
+    // The key FX0A saw go down, it finishes once that key is released
+    key_wait: Option<u8>,
+
     // Program to load
     program: &'c str,
 
-    // Clock Speed of the chip in ms
-    clock_speed: u64,
+    // Where programs are loaded and start running, 0x200 or 0x600 for ETI-660 ROMs
+    load_address: u16,
+
+    // Interpreter specific behaviour
+    quirks: Quirks,
+
+    // SUPER-CHIP RPL user flags, kept by the HP-48 between programs
+    rpl: [u8; 16],
+
+    // Set by 00FD, the program asked the interpreter to quit
+    exited: bool,
+
+    // XO-CHIP mode, 64K of memory
+    xochip: bool,
+
+    // XO-CHIP audio pattern buffer and pitch, with a flag telling the
+    // frontend they changed
+    pattern: [u8; 16],
+    pitch: u8,
+    pattern_flag: bool,
+
+    // Where CXNN gets its random bytes
+    rng: Box<dyn Rng>,
+
+    // Random bytes CXNN used while recording, and the ones it must use
+    // next when replaying a movie
+    random_log: Option<Vec<u8>>,
+    random_replay: VecDeque<u8>,
 }
 
 impl<'c> Chipate<'c> {
     pub fn init(&mut self) {
         debug!("Initialize Chip");
         // pc     = 0x200;  // Program counter starts at 0x200
-        self.pc = 0x200;
+        self.pc = self.load_address;
         // opcode = 0;      // Reset current opcode
         self.opcode = 0x0;
         // I      = 0;      // Reset index register
@@ -74,49 +112,250 @@ impl<'c> Chipate<'c> {
         // // Reset timers
     }
 
-    pub fn load_program(&mut self, program: &str) {
+    /// Loads `programs/<program>` from the current directory.
+    pub fn load_program(&mut self, program: &str) -> Result<(), EmuError> {
         debug!("Loading program {}", program);
 
         // We assume that we are in a valid directory.
-        let mut p = env::current_dir().unwrap();
+        let mut p = env::current_dir()?;
         p.push("programs");
         p.push(program);
 
-        let mut f = File::open(p).expect("Unable to open file");
+        self.load_rom_from_path(p)
+    }
+
+    /// Loads the ROM file at `path`.
+    pub fn load_rom_from_path<P: AsRef<Path>>(&mut self, path: P) -> Result<(), EmuError> {
+        debug!("Loading ROM {}", path.as_ref().display());
+
+        let mut f = File::open(path)?;
         let mut b = Vec::new();
 
-        f.read_to_end(&mut b).expect("Unable to read file");
+        f.read_to_end(&mut b)?;
+
+        self.load_rom_from_bytes(&b)
+    }
 
-        for i in 0..b.len() {
-            self.memory[i + 512] = b[i];
+    /// Copies the ROM to the load address and points the PC at it.
+    pub fn load_rom_from_bytes(&mut self, rom: &[u8]) -> Result<(), EmuError> {
+        let start = self.load_address as usize;
+        let max = self.memory.len().saturating_sub(start);
+        if rom.len() > max {
+            return Err(EmuError::RomTooLarge { size: rom.len(), max });
         }
 
+        self.memory[start..start + rom.len()].copy_from_slice(rom);
+        self.pc = self.load_address;
+        debug!("Loaded {} bytes at 0x{:X}", rom.len(), start);
+        Ok(())
+    }
+
+    /// Sets where ROMs get loaded, call before loading one.
+    pub fn set_load_address(&mut self, addr: u16) {
+        self.load_address = addr;
+    }
+
+    pub fn load_address(&self) -> u16 {
+        self.load_address
     }
 
-    pub fn set_clock_speed(&mut self, s: u64) {
-        self.clock_speed = s;
+    pub fn set_quirks(&mut self, q: Quirks) {
+        debug!("Quirks: {:?}", q);
+        self.quirks = q;
     }
 
-    pub fn emulate_cycle(&mut self) {
-        self.fetch_opcode();
-        self.decode_opcode();
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    /// RPL user flags written by FX75, frontends persist these between runs.
+    pub fn rpl_flags(&self) -> [u8; 16] {
+        self.rpl
+    }
+
+    pub fn set_rpl_flags(&mut self, flags: [u8; 16]) {
+        self.rpl = flags;
+    }
+
+    /// Switches XO-CHIP mode on or off, resizing memory to 64K or 4K.
+    pub fn set_xochip(&mut self, on: bool) {
+        debug!("XO-CHIP: {}", on);
+        self.xochip = on;
+        self.memory.resize(if on { 0x10000 } else { 0x1000 }, 0);
+    }
+
+    pub fn xochip(&self) -> bool {
+        self.xochip
+    }
+
+    /// True once the program executed 00FD.
+    pub fn exited(&self) -> bool {
+        self.exited
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    /// The last opcode fetched.
+    pub fn opcode(&self) -> u16 {
+        self.opcode
+    }
+
+    pub fn v(&self) -> &[u8; 16] {
+        &self.v
+    }
 
-        let sleep = time::Duration::from_millis(self.clock_speed);
-        thread::sleep(sleep);
+    pub fn set_v(&mut self, x: usize, value: u8) {
+        self.v[x & 0xF] = value;
+    }
+
+    pub fn i(&self) -> u16 {
+        self.i
+    }
+
+    pub fn set_i(&mut self, i: u16) {
+        self.i = i;
+    }
+
+    /// Return addresses, innermost call last.
+    pub fn stack(&self) -> &[u16] {
+        &self.stack
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    pub fn key(&self) -> &[u8; 16] {
+        &self.key
+    }
+
+    /// Replaces the random source CXNN draws from, see `rng`.
+    pub fn set_rng(&mut self, rng: Box<dyn Rng>) {
+        self.rng = rng;
+    }
+
+    /// Shorthand for `set_rng` with a `SeededRng`.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.set_rng(Box::new(SeededRng::new(seed)));
+    }
+
+    /// Starts or stops keeping the random bytes CXNN draws, see `take_random_log`.
+    pub fn record_random(&mut self, on: bool) {
+        self.random_log = if on { Some(Vec::new()) } else { None };
+    }
+
+    /// Random bytes drawn since the last call while recording.
+    pub fn take_random_log(&mut self) -> Vec<u8> {
+        self.random_log.as_mut().map(mem::take).unwrap_or_default()
+    }
+
+    /// Queues bytes for CXNN to use instead of fresh random ones, for replays.
+    pub fn feed_random(&mut self, bytes: &[u8]) {
+        self.random_replay.extend(bytes);
+    }
+
+    /// Snapshot of the whole machine, see `state::State`.
+    pub fn save_state(&self) -> State {
+        State {
+            opcode: self.opcode,
+            memory: self.memory.clone(),
+            v: self.v,
+            i: self.i,
+            pc: self.pc,
+            stack: self.stack.clone(),
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            key: self.key,
+            key_wait: self.key_wait,
+            display: self.display.clone(),
+            load_address: self.load_address,
+            quirks: self.quirks,
+            rpl: self.rpl,
+            exited: self.exited,
+            xochip: self.xochip,
+            pattern: self.pattern,
+            pitch: self.pitch,
+        }
+    }
+
+    /// Puts the machine back the way `save_state` found it.
+    pub fn load_state(&mut self, state: &State) {
+        self.opcode = state.opcode;
+        self.memory = state.memory.clone();
+        self.v = state.v;
+        self.i = state.i;
+        self.pc = state.pc;
+        self.stack = state.stack.clone();
+        self.delay_timer = state.delay_timer;
+        self.sound_timer = state.sound_timer;
+        self.key = state.key;
+        self.key_wait = state.key_wait;
+        self.display = state.display.clone();
+        self.load_address = state.load_address;
+        self.quirks = state.quirks;
+        self.rpl = state.rpl;
+        self.exited = state.exited;
+        self.xochip = state.xochip;
+        self.pattern = state.pattern;
+        self.pitch = state.pitch;
+        // Only programs that used the pattern buffer have anything in it
+        self.pattern_flag = self.pattern != [0; 16];
+    }
+
+    pub fn emulate_cycle(&mut self) -> Result<(), EmuError> {
+        if self.exited {
+            return Ok(());
+        }
+
+        self.fetch_opcode()?;
+        self.decode_opcode()
+    }
 
+    /// Counts the delay and sound timers down, call this at 60 Hz.
+    pub fn tick_timers(&mut self) {
         if self.delay_timer > 0 { self.delay_timer -= 1; }
 
-        if self.sound_timer > 0 {
-            if self.sound_timer == 1 { println!("BEEP!\n"); }
-            self.sound_timer -= 1;
+        if self.sound_timer > 0 { self.sound_timer -= 1; }
+    }
+
+    /// Saves the keypad state reported by the frontend.
+    pub fn set_keys<I: InputSource>(&mut self, input: &mut I) {
+        input.poll(&mut self.key);
+    }
+
+    /// Hands the framebuffer to the frontend if anything was drawn since the last call.
+    pub fn draw_screen<V: VideoSink>(&mut self, video: &mut V) {
+        if !self.display.draw_flag() {
+            return;
         }
+        video.render(&self.display);
+        self.display.clear_draw_flag();
     }
 
-    pub fn set_keys(&mut self) {
-        // debug!("Saving Key State")
+    /// Keeps the frontend tone playing for as long as the sound timer runs.
+    pub fn play_sound<A: AudioSink>(&mut self, audio: &mut A) {
+        if self.pattern_flag {
+            audio.set_pattern(&self.pattern, self.pitch);
+            self.pattern_flag = false;
+        }
+        audio.set_tone(self.sound_timer > 0);
     }
 
-    pub fn fetch_opcode(&mut self) {
+    pub fn fetch_opcode(&mut self) -> Result<(), EmuError> {
+        if self.pc as usize + 1 >= self.memory.len() {
+            return Err(EmuError::PcOutOfBounds { pc: self.pc });
+        }
+
         let op_a = self.memory[self.pc as usize];
         self.opcode = op_a as u16;
         self.opcode = self.opcode << 8;
@@ -126,9 +365,10 @@ impl<'c> Chipate<'c> {
         // debug!("location: 0x{:X} data: 0x{:X}", self.pc, op_b);
 
         self.opcode = self.opcode | op_b;
+        Ok(())
     }
 
-    pub fn decode_opcode(&mut self) {
+    pub fn decode_opcode(&mut self) -> Result<(), EmuError> {
         let op = self.opcode & 0xF000;
 
         match op {
@@ -137,110 +377,282 @@ impl<'c> Chipate<'c> {
             0x2000 => self._2nnn_opcode(),
             0x3000 => self._3xnn_opcode(),
             0x4000 => self._4xnn_opcode(),
-            0x5000 => self._5xy0_opcode(),
+            0x5000 => self._5_opcodes(),
             0x6000 => self._6xnn_opcode(),
             0x7000 => self._7xnn_opcode(),
+            0x8000 => self._8_opcodes(),
+            0x9000 => self._9xy0_opcode(),
             0xA000 => self._annn_opcode(),
+            0xB000 => self._bnnn_opcode(),
+            0xC000 => self._cxnn_opcode(),
             0xD000 => self._dxyn_opcode(),
             0xE000 => self._e_opcodes(),
             0xF000 => self._f_opcodes(),
             _ => {
-                // Using the catch all as a NOOP
                 info!("Catch all: 0x{:X}", self.opcode);
-                self.increase_pc();
+                Err(self.invalid_opcode())
             }
         }
     }
 
     pub fn increase_pc(&mut self) {
-        self.pc += 2;
+        // Running off the end is caught by the next fetch
+        self.pc = self.pc.wrapping_add(2);
         debug!("Program Counter: 0x{:X}", self.pc);
     }
 
-    pub fn _0_opcodes(&mut self) {
+    /// Builds the error for an opcode that does not decode.
+    fn invalid_opcode(&self) -> EmuError {
+        EmuError::InvalidOpcode {
+            pc: self.pc,
+            opcode: self.opcode,
+        }
+    }
+
+    /// Checks that `len` bytes starting at `from` are inside memory.
+    fn check_memory(&self, from: usize, len: usize) -> Result<(), EmuError> {
+        if from + len > self.memory.len() {
+            return Err(EmuError::MemoryOutOfBounds {
+                pc: self.pc,
+                opcode: self.opcode,
+                addr: from.max(self.memory.len()),
+            });
+        }
+        Ok(())
+    }
+
+    /// Steps over the next instruction, the skip half of the conditional opcodes.
+    /// F000 NNNN is four bytes long so it is skipped as a whole.
+    pub fn skip_next(&mut self) {
+        let next = self.pc as usize + 2;
+        if self.memory.get(next) == Some(&0xF0) && self.memory.get(next + 1) == Some(&0x00) {
+            self.increase_pc();
+        }
+        self.increase_pc();
+    }
+
+    pub fn _0_opcodes(&mut self) -> Result<(), EmuError> {
         let sub_op = self.opcode & 0x00FF;
 
         match sub_op{
             0x00E0 => self._00e0_opcode(),
             0x00EE => self._00ee_opcode(),
+            0x00FB => self._00fb_opcode(),
+            0x00FC => self._00fc_opcode(),
+            0x00FD => self._00fd_opcode(),
+            0x00FE => self._00fe_opcode(),
+            0x00FF => self._00ff_opcode(),
+            _ if self.opcode & 0xFFF0 == 0x00C0 => self._00cn_opcode(),
+            _ if self.opcode & 0xFFF0 == 0x00D0 => self._00dn_opcode(),
             _ => {
                 /// 0NNN 	Call 		Calls RCA 1802 program at address NNN. Not necessary for most ROMs.
                 info!("Calls RCA 1802 program at address NNN. 0x{:X}", self.opcode);
                 self.increase_pc();
+                Ok(())
             }
         }
     }
 
     /// 00E0 	Display 	disp_clear() 	Clears the screen.
-    pub fn _00e0_opcode(&mut self) {
+    pub fn _00e0_opcode(&mut self) -> Result<(), EmuError> {
         self.display.clear();
         self.increase_pc();
+        Ok(())
     }
 
     /// 00EE 	Flow 	return; 	Returns from a subroutine.
-    pub fn _00ee_opcode(&mut self) {
+    pub fn _00ee_opcode(&mut self) -> Result<(), EmuError> {
         info!("Return from sub routine");
-        let addr = self.stack.pop();
-        self.pc = addr.unwrap() as u16;
-        debug!("Returning to {:X}", addr.unwrap());
+        let addr = match self.stack.pop() {
+            Some(addr) => addr,
+            None => {
+                return Err(EmuError::StackUnderflow {
+                    pc: self.pc,
+                    opcode: self.opcode,
+                })
+            }
+        };
+        self.pc = addr;
+        debug!("Returning to {:X}", addr);
+        self.increase_pc();
+        Ok(())
+    }
+
+    /// 00CN 	Display 	scroll_down(N) 	Scrolls the screen down N pixels. (SUPER-CHIP)
+    pub fn _00cn_opcode(&mut self) -> Result<(), EmuError> {
+        info!("00CN: 0x{:X}", self.opcode);
+        let n = (self.opcode & 0x000F) as usize;
+        self.display.scroll_down(n);
+        self.increase_pc();
+        Ok(())
+    }
+
+    /// 00DN 	Display 	scroll_up(N) 	Scrolls the screen up N pixels. (XO-CHIP)
+    pub fn _00dn_opcode(&mut self) -> Result<(), EmuError> {
+        info!("00DN: 0x{:X}", self.opcode);
+        let n = (self.opcode & 0x000F) as usize;
+        self.display.scroll_up(n);
+        self.increase_pc();
+        Ok(())
+    }
+
+    /// 00FB 	Display 	scroll_right() 	Scrolls the screen right 4 pixels. (SUPER-CHIP)
+    pub fn _00fb_opcode(&mut self) -> Result<(), EmuError> {
+        info!("00FB: 0x{:X}", self.opcode);
+        self.display.scroll_right(4);
+        self.increase_pc();
+        Ok(())
+    }
+
+    /// 00FC 	Display 	scroll_left() 	Scrolls the screen left 4 pixels. (SUPER-CHIP)
+    pub fn _00fc_opcode(&mut self) -> Result<(), EmuError> {
+        info!("00FC: 0x{:X}", self.opcode);
+        self.display.scroll_left(4);
         self.increase_pc();
+        Ok(())
+    }
+
+    /// 00FD 	Flow 	exit() 	Exits the interpreter. (SUPER-CHIP)
+    pub fn _00fd_opcode(&mut self) -> Result<(), EmuError> {
+        info!("00FD: 0x{:X}", self.opcode);
+        self.exited = true;
+        debug!("Program exited");
+        Ok(())
+    }
+
+    /// 00FE 	Display 	lores() 	Switches to the 64x32 resolution. (SUPER-CHIP)
+    pub fn _00fe_opcode(&mut self) -> Result<(), EmuError> {
+        info!("00FE: 0x{:X}", self.opcode);
+        self.display.set_hires(false);
+        self.increase_pc();
+        Ok(())
+    }
+
+    /// 00FF 	Display 	hires() 	Switches to the 128x64 resolution. (SUPER-CHIP)
+    pub fn _00ff_opcode(&mut self) -> Result<(), EmuError> {
+        info!("00FF: 0x{:X}", self.opcode);
+        self.display.set_hires(true);
+        self.increase_pc();
+        Ok(())
     }
 
     /// 1NNN 	Flow 	goto NNN; 	Jumps to address NNN.
-    pub fn _1nnn_opcode(&mut self) {
+    pub fn _1nnn_opcode(&mut self) -> Result<(), EmuError> {
         info!("1NNN: 0x{:X}", self.opcode);
         let addr = self.opcode & 0x0FFF;
         self.pc = addr;
         debug!("Jumping to 0x{:X}", addr);
+        Ok(())
     }
 
     /// 2NNN 	Flow 	*(0xNNN)() 	Calls subroutine at NNN.
-    pub fn _2nnn_opcode(&mut self) {
+    pub fn _2nnn_opcode(&mut self) -> Result<(), EmuError> {
         info!("2NNN: 0x{:X}", self.opcode);
         debug!("Calls subroutine at: 0x{:X}", (self.opcode & 0x0FFF));
+        if self.stack.len() >= STACK_SIZE {
+            return Err(EmuError::StackOverflow {
+                pc: self.pc,
+                opcode: self.opcode,
+            });
+        }
         self.stack.push(self.pc);
         self.pc = self.opcode & 0x0FFF;
+        Ok(())
     }
 
     /// 3XNN	Cond	if(Vx==NN)	Skips the next instruction if VX equals NN.
     /// (Usually the next instruction is a jump to skip a code block)
-    pub fn _3xnn_opcode(&mut self) {
+    pub fn _3xnn_opcode(&mut self) -> Result<(), EmuError> {
         info!("3XNN: 0x{:X}", self.opcode);
         let x = ((self.opcode & 0x0F00) >> 8) as usize;
         let nn = (self.opcode & 0x00FF) as u8;
         if self.v[x] == nn  {
-            self.increase_pc();
+            self.skip_next();
         }
         self.increase_pc();
+        Ok(())
     }
 
     /// 4XNN	Cond	if(Vx!=NN)	Skips the next instruction if VX doesn't equal NN.
     /// (Usually the next instruction is a jump to skip a code block)
-    pub fn _4xnn_opcode(&mut self) {
+    pub fn _4xnn_opcode(&mut self) -> Result<(), EmuError> {
         info!("4XNN: 0x{:X}", self.opcode);
         let x = ((self.opcode & 0x0F00) >> 8) as usize;
         let nn = (self.opcode & 0x00FF) as u8;
         if self.v[x] != nn  {
-            self.increase_pc();
+            self.skip_next();
         }
         self.increase_pc();
+        Ok(())
     }
 
     /// 5XY0	Cond	if(Vx==Vy)	Skips the next instruction if VX equals VY.
     /// (Usually the next instruction is a jump to skip a code block)
-    pub fn _5xy0_opcode(&mut self) {
+    pub fn _5xy0_opcode(&mut self) -> Result<(), EmuError> {
         info!("5XY0: 0x{:X}", self.opcode);
         let x = ((self.opcode & 0x0F00) >> 8) as usize;
         let y = ((self.opcode & 0x00F0) >> 4) as usize;
 
         if self.v[x] == self.v[y] {
-            self.increase_pc();
+            self.skip_next();
+        }
+        self.increase_pc();
+        Ok(())
+    }
+
+    /// 5XY2	MEM	save(Vx..Vy)	Stores VX to VY (including VY) in memory starting at address I,
+    /// in reverse order when X is bigger than Y. I is left alone. (XO-CHIP)
+    pub fn _5xy2_opcode(&mut self) -> Result<(), EmuError> {
+        info!("5XY2: 0x{:X}", self.opcode);
+        let x = ((self.opcode & 0x0F00) >> 8) as usize;
+        let y = ((self.opcode & 0x00F0) >> 4) as usize;
+        let from = self.i as usize;
+        let range = register_range(x, y);
+        self.check_memory(from, range.len())?;
+
+        for (n, &r) in range.iter().enumerate() {
+            self.memory[from + n] = self.v[r];
         }
+
         self.increase_pc();
+        Ok(())
+    }
+
+    /// 5XY3	MEM	load(Vx..Vy)	Fills VX to VY (including VY) from memory starting at address I,
+    /// in reverse order when X is bigger than Y. I is left alone. (XO-CHIP)
+    pub fn _5xy3_opcode(&mut self) -> Result<(), EmuError> {
+        info!("5XY3: 0x{:X}", self.opcode);
+        let x = ((self.opcode & 0x0F00) >> 8) as usize;
+        let y = ((self.opcode & 0x00F0) >> 4) as usize;
+        let from = self.i as usize;
+        let range = register_range(x, y);
+        self.check_memory(from, range.len())?;
+
+        for (n, &r) in range.iter().enumerate() {
+            self.v[r] = self.memory[from + n];
+        }
+
+        self.increase_pc();
+        Ok(())
+    }
+
+    pub fn _5_opcodes(&mut self) -> Result<(), EmuError> {
+        let sub_op = self.opcode & 0x000F;
+        debug!("Decode: 0x{:X}", sub_op);
+
+        match sub_op{
+            0x0000 => self._5xy0_opcode(),
+            0x0002 => self._5xy2_opcode(),
+            0x0003 => self._5xy3_opcode(),
+            _ => {
+                info!("Catch all 0x5xxx: 0x{:X}", self.opcode);
+                Err(self.invalid_opcode())
+            }
+        }
     }
 
     /// 6XNN 	Const 	Vx = NN 	Sets VX to NN.
-    pub fn _6xnn_opcode(&mut self) {
+    pub fn _6xnn_opcode(&mut self) -> Result<(), EmuError> {
         info!("6XNN: 0x{:X}", self.opcode);
         let mut reg = self.opcode & 0x0F00;
         reg = reg >> 8;
@@ -250,10 +662,11 @@ impl<'c> Chipate<'c> {
         self.v[reg as usize] = nn as u8;
         self.increase_pc();
         debug!("Set V{:X} (V{}) 0x{:X}", reg, reg, nn);
+        Ok(())
     }
 
     /// 7XNN 	Const 	Vx += NN 	Adds NN to VX.
-    pub fn _7xnn_opcode(&mut self) {
+    pub fn _7xnn_opcode(&mut self) -> Result<(), EmuError> {
         info!("7XNN: 0x{:X}", self.opcode);
 
         let mut reg = self.opcode & 0x0F00;
@@ -267,10 +680,11 @@ impl<'c> Chipate<'c> {
         self.v[reg as usize] = buf as u8;
         self.increase_pc();
         debug!("Add {:X} to V{:X} (V{}) = {:X}", nn, reg, reg, self.v[reg as usize]);
+        Ok(())
     }
 
     /// 8XY0	Assign	Vx=Vy	Sets VX to the value of VY.
-    pub fn _8xy0_opcode(&mut self) {
+    pub fn _8xy0_opcode(&mut self) -> Result<(), EmuError> {
         info!("8XY0: 0x{:X}", self.opcode);
         let x = (self.opcode & 0x0F00) >> 8;
         let y = (self.opcode & 0x00F0) >> 4;
@@ -279,128 +693,153 @@ impl<'c> Chipate<'c> {
 
         self.increase_pc();
         debug!("Assign	Vx=Vy	Sets VX to the value of VY");
+        Ok(())
     }
 
     /// 8XY1	BitOp	Vx=Vx|Vy	Sets VX to VX or VY. (Bitwise OR operation)
-    pub fn _8xy1_opcode(&mut self) {
+    pub fn _8xy1_opcode(&mut self) -> Result<(), EmuError> {
         info!("8XY1: 0x{:X}", self.opcode);
 
         let x = (self.opcode & 0x0F00) >> 8;
         let y = (self.opcode & 0x00F0) >> 4;
 
         self.v[x as usize] |= self.v[y as usize];
+        if self.quirks.vf_reset {
+            self.v[0xF as usize] = 0;
+        }
 
         self.increase_pc();
         debug!("BitOp	Vx=Vx|Vy	Sets VX to VX or VY. (Bitwise OR operation)");
+        Ok(())
     }
 
     /// 8XY2	BitOp	Vx=Vx&Vy	Sets VX to VX and VY. (Bitwise AND operation)
-    pub fn _8xy2_opcode(&mut self) {
+    pub fn _8xy2_opcode(&mut self) -> Result<(), EmuError> {
         info!("8XY2: 0x{:X}", self.opcode);
 
         let x = (self.opcode & 0x0F00) >> 8;
         let y = (self.opcode & 0x00F0) >> 4;
 
         self.v[x as usize] &= self.v[y as usize];
+        if self.quirks.vf_reset {
+            self.v[0xF as usize] = 0;
+        }
 
         self.increase_pc();
         debug!("8XY2	BitOp	Vx=Vx&Vy	Sets VX to VX and VY. (Bitwise AND operation)");
+        Ok(())
     }
 
     /// 8XY3	BitOp	Vx=Vx^Vy	Sets VX to VX xor VY.
-    pub fn _8xy3_opcode(&mut self) {
+    pub fn _8xy3_opcode(&mut self) -> Result<(), EmuError> {
         info!("8XY3: 0x{:X}", self.opcode);
 
         let x = (self.opcode & 0x0F00) >> 8;
         let y = (self.opcode & 0x00F0) >> 4;
 
         self.v[x as usize] ^= self.v[y as usize];
+        if self.quirks.vf_reset {
+            self.v[0xF as usize] = 0;
+        }
 
         self.increase_pc();
         debug!("8XY3	BitOp	Vx=Vx^Vy	Sets VX to VX xor VY.");
+        Ok(())
     }
 
     /// 8XY4	Math	Vx += Vy	Adds VY to VX. VF is set to 1 when there's a carry, and to 0 when there isn't.
-    pub fn _8xy4_opcode(&mut self) {
+    pub fn _8xy4_opcode(&mut self) -> Result<(), EmuError> {
         info!("8XY4: 0x{:X}", self.opcode);
 
         let x = ((self.opcode & 0x0F00) >> 8) as usize;
         let y = ((self.opcode & 0x00F0) >> 4) as usize;
 
-        if self.v[x] > 0xFF - self.v[y] {
+        // Easy way to add our buffers without overflow
+        let buf: u32 = self.v[x] as u32 + self.v[y] as u32;
+        self.v[x as usize] = buf as u8;
+
+        // VF is written last so the flag wins when X is F
+        if buf > 0xFF {
             self.v[0xF as usize] = 1;
         } else {
             self.v[0xF as usize] = 0;
         }
 
-        // Easy way to add our buffers without overflow
-        let buf: u32 = self.v[x] as u32 + self.v[y] as u32;
-        self.v[x as usize] = buf as u8;
-
         self.increase_pc();
+        Ok(())
     }
 
     /// 8XY5	Math	Vx -= Vy	VY is subtracted from VX. VF is set to 0 when there's
     /// a borrow, and 1 when there isn't.
-    pub fn _8xy5_opcode(&mut self) {
+    pub fn _8xy5_opcode(&mut self) -> Result<(), EmuError> {
         info!("8XY5: 0x{:X}", self.opcode);
 
         let x = ((self.opcode & 0x0F00) >> 8) as usize;
         let y = ((self.opcode & 0x00F0) >> 4) as usize;
 
-        if self.v[y] > self.v[x] {
-            self.v[0xF as usize] = 0;
-        } else {
-            self.v[0xF as usize] = 1;
-        }
+        let no_borrow = if self.v[y] > self.v[x] { 0 } else { 1 };
 
-        self.v[x] -= self.v[y];
+        self.v[x] = self.v[x].wrapping_sub(self.v[y]);
+        self.v[0xF as usize] = no_borrow;
         self.increase_pc();
+        Ok(())
     }
 
     /// 8XY6	BitOp	Vx >> 1	Shifts VX right by one. VF is set to the value of the
     /// least significant bit of VX before the shift.[2]
-    pub fn _8xy6_opcode(&mut self) {
+    pub fn _8xy6_opcode(&mut self) -> Result<(), EmuError> {
         info!("8XY6: 0x{:X}", self.opcode);
         let x = ((self.opcode & 0x0F00) >> 8) as usize;
+        let y = ((self.opcode & 0x00F0) >> 4) as usize;
+
+        if self.quirks.shift_vy {
+            self.v[x] = self.v[y];
+        }
 
-        self.v[0xF as usize] = self.v[x] & 0x1;
+        let lsb = self.v[x] & 0x1;
         self.v[x] = self.v[x] >> 1;
+        self.v[0xF as usize] = lsb;
 
         self.increase_pc();
+        Ok(())
     }
 
     /// 8XY7	Math	Vx=Vy-Vx	Sets VX to VY minus VX. VF is set to 0 when there's a borrow,
     ///and 1 when there isn't.
-    pub fn _8xy7_opcode(&mut self) {
+    pub fn _8xy7_opcode(&mut self) -> Result<(), EmuError> {
         info!("8XY7: 0x{:x}", self.opcode);
         let x = ((self.opcode & 0x0F00) >> 8) as usize;
         let y = ((self.opcode & 0x00F0) >> 4) as usize;
 
-        if self.v[x] > self.v[y] {
-            self.v[0xF as usize] = 0;
-        } else {
-            self.v[0xF as usize] = 1;
-        }
+        let no_borrow = if self.v[x] > self.v[y] { 0 } else { 1 };
 
-        self.v[x] = self.v[y] - self.v[x];
+        self.v[x] = self.v[y].wrapping_sub(self.v[x]);
+        self.v[0xF as usize] = no_borrow;
         self.increase_pc();
+        Ok(())
     }
 
     /// 8XYE	BitOp	Vx << 1	Shifts VX left by one. VF is set to the value of the most significant
     /// bit of VX before the shift.[2]
-    pub fn _8xye_opcode(&mut self) {
+    pub fn _8xye_opcode(&mut self) -> Result<(), EmuError> {
         info!("8XYE: 0x{:x}", self.opcode);
 
         let x = ((self.opcode & 0x0F00) >> 8) as usize;
+        let y = ((self.opcode & 0x00F0) >> 4) as usize;
 
-        self.v[0xF as usize] = self.v[x] >> 7;
+        if self.quirks.shift_vy {
+            self.v[x] = self.v[y];
+        }
+
+        let msb = self.v[x] >> 7;
         self.v[x] = self.v[x] << 1;
+        self.v[0xF as usize] = msb;
 
         self.increase_pc();
+        Ok(())
     }
 
-    pub fn _8_opcodes(&mut self) {
+    pub fn _8_opcodes(&mut self) -> Result<(), EmuError> {
         let sub_op = self.opcode & 0x000F;
         debug!("Decode: 0x{:X}", sub_op);
 
@@ -415,24 +854,70 @@ impl<'c> Chipate<'c> {
             0x0007 => self._8xy7_opcode(),
             0x000e => self._8xye_opcode(),
             _ => {
-                // Using the catch all as a NOOP
                 info!("Catch all 0x8xxx: 0x{:X}", self.opcode);
-                self.increase_pc();
+                Err(self.invalid_opcode())
             }
         }
     }
 
+    /// 9XY0	Cond	if(Vx!=Vy)	Skips the next instruction if VX doesn't equal VY.
+    /// (Usually the next instruction is a jump to skip a code block)
+    pub fn _9xy0_opcode(&mut self) -> Result<(), EmuError> {
+        info!("9XY0: 0x{:X}", self.opcode);
+        let x = ((self.opcode & 0x0F00) >> 8) as usize;
+        let y = ((self.opcode & 0x00F0) >> 4) as usize;
+
+        if self.v[x] != self.v[y] {
+            self.skip_next();
+        }
+        self.increase_pc();
+        Ok(())
+    }
+
 
     /// ANNN 	MEM 	I = NNN 	Sets I to the address NNN.
-    pub fn _annn_opcode(&mut self) {
+    pub fn _annn_opcode(&mut self) -> Result<(), EmuError> {
         info!("ANNN: 0x{:X}", self.opcode);
         self.i = self.opcode & 0x0FFF;
         self.increase_pc();
         debug!("Set I: {:X}", self.i);
+        Ok(())
+    }
+
+    /// BNNN 	Flow 	PC=V0+NNN 	Jumps to the address NNN plus V0.
+    pub fn _bnnn_opcode(&mut self) -> Result<(), EmuError> {
+        info!("BNNN: 0x{:X}", self.opcode);
+        let reg = if self.quirks.jump_vx {
+            ((self.opcode & 0x0F00) >> 8) as usize
+        } else {
+            0
+        };
+        let addr = (self.opcode & 0x0FFF) + self.v[reg] as u16;
+        self.pc = addr;
+        debug!("Jumping to 0x{:X}", addr);
+        Ok(())
+    }
+
+    /// CXNN 	Rand 	Vx=rand()&NN 	Sets VX to the result of a bitwise and operation on a random
+    /// number (Typically: 0 to 255) and NN.
+    pub fn _cxnn_opcode(&mut self) -> Result<(), EmuError> {
+        info!("CXNN: 0x{:X}", self.opcode);
+        let x = ((self.opcode & 0x0F00) >> 8) as usize;
+        let nn = (self.opcode & 0x00FF) as u8;
+
+        let r = self.random_replay.pop_front().unwrap_or_else(|| self.rng.next_u8());
+        if let Some(ref mut log) = self.random_log {
+            log.push(r);
+        }
+
+        self.v[x] = r & nn;
+        self.increase_pc();
+        debug!("Set V{:X} to random 0x{:X}", x, self.v[x]);
+        Ok(())
     }
 
 
-    pub fn _e_opcodes(&mut self) {
+    pub fn _e_opcodes(&mut self) -> Result<(), EmuError> {
         let sub_op = self.opcode & 0x00FF;
         debug!("Decode: 0x{:X}", sub_op);
 
@@ -440,44 +925,104 @@ impl<'c> Chipate<'c> {
             0x009E => self._ex9e_opcode(),
             0x00A1 => self._exa1_opcode(),
             _ => {
-                // Using the catch all as a NOOP
                 info!("Catch all 0xENNN: 0x{:X}", self.opcode);
-                self.increase_pc();
+                Err(self.invalid_opcode())
             }
         }
     }
 
-    pub fn _dxyn_opcode(&mut self) {
+    /// DXYN 	Disp 	draw(Vx,Vy,N) 	Draws an 8xN sprite from I at (VX, VY), VF is set on collision.
+    /// DXY0 draws a 16x16 sprite instead. (SUPER-CHIP)
+    pub fn _dxyn_opcode(&mut self) -> Result<(), EmuError> {
         info!("DXYN: 0x{:X}", self.opcode);
         let x = self.v[((self.opcode & 0x0F00) >> 8) as usize];
         let y = self.v[((self.opcode & 0x00F0) >> 4) as usize];
+        let n = (self.opcode & 0x000F) as usize;
 
+        // Every selected XO-CHIP plane reads its own copy of the sprite
+        let planes = self.display.plane_count();
         let from = self.i as usize;
-        let to = from + (self.opcode & 0x000F) as usize;
+        self.check_memory(from, if n == 0 { 32 } else { n } * planes)?;
 
-        self.v[0xF] = self.display.draw(x as usize, y as usize, &self.memory[from..to]);
+        self.v[0xF] = if n == 0 {
+            self.display.draw_wide(x as usize,
+                                   y as usize,
+                                   &self.memory[from..from + 32 * planes],
+                                   self.quirks.clip_sprites)
+        } else {
+            self.display.draw(x as usize,
+                              y as usize,
+                              &self.memory[from..from + n * planes],
+                              self.quirks.clip_sprites)
+        };
 
         self.increase_pc();
+        Ok(())
     }
 
     /// EX9E 	KeyOp 	if(key()==Vx) 	Skips the next instruction if the key stored in VX is pressed.
     /// (Usually the next instruction is a jump to skip a code block)
-    pub fn _ex9e_opcode(&mut self) {
+    pub fn _ex9e_opcode(&mut self) -> Result<(), EmuError> {
         info!("EX9E: 0x{:X}", self.opcode);
-        debug!("TODO: check for key");
+        let x = ((self.opcode & 0x0F00) >> 8) as usize;
+        let key = (self.v[x] & 0x0F) as usize;
+
+        if self.key[key] != 0 {
+            self.skip_next();
+        }
+        self.increase_pc();
+        Ok(())
     }
 
     /// EXA1 	KeyOp 	if(key()!=Vx) 	Skips the next instruction if the key stored in VX isn't pressed.
     /// (Usually the next instruction is a jump to skip a code block)
-    pub fn _exa1_opcode(&mut self) {
+    pub fn _exa1_opcode(&mut self) -> Result<(), EmuError> {
         info!("EXA1: 0x{:X}", self.opcode);
-        debug!("TODO: check for key");
+        let x = ((self.opcode & 0x0F00) >> 8) as usize;
+        let key = (self.v[x] & 0x0F) as usize;
+
+        if self.key[key] == 0 {
+            self.skip_next();
+        }
         self.increase_pc();
+        Ok(())
+    }
+
+    /// F000 NNNN 	MEM 	I = NNNN 	Sets I to the 16 bit address in the next two bytes. (XO-CHIP)
+    pub fn _f000_opcode(&mut self) -> Result<(), EmuError> {
+        info!("F000: 0x{:X}", self.opcode);
+        let at = self.pc as usize + 2;
+        self.check_memory(at, 2)?;
+        self.i = (self.memory[at] as u16) << 8 | self.memory[at + 1] as u16;
+
         self.increase_pc();
+        self.increase_pc();
+        debug!("Set I: {:X}", self.i);
+        Ok(())
+    }
+
+    /// FN01 	Display 	plane(N) 	Selects the drawing planes by bitmask N. (XO-CHIP)
+    pub fn _fn01_opcode(&mut self) -> Result<(), EmuError> {
+        info!("FN01: 0x{:X}", self.opcode);
+        let n = ((self.opcode & 0x0F00) >> 8) as u8;
+        self.display.select_planes(n);
+        self.increase_pc();
+        Ok(())
+    }
+
+    /// F002 	Sound 	audio(&I) 	Loads the 16 byte audio pattern buffer from memory at I. (XO-CHIP)
+    pub fn _f002_opcode(&mut self) -> Result<(), EmuError> {
+        info!("F002: 0x{:X}", self.opcode);
+        let from = self.i as usize;
+        self.check_memory(from, 16)?;
+        self.pattern.copy_from_slice(&self.memory[from..from + 16]);
+        self.pattern_flag = true;
+        self.increase_pc();
+        Ok(())
     }
 
     /// FX07 	Timer 	Vx = get_delay() 	Sets VX to the value of the delay timer.
-    pub fn _fx07_opcode(&mut self) {
+    pub fn _fx07_opcode(&mut self) -> Result<(), EmuError> {
         info!("FX07: 0x{:X}", self.opcode);
 
         let mut reg = self.opcode & 0x0F00;
@@ -485,18 +1030,32 @@ impl<'c> Chipate<'c> {
         self.v[reg as usize] = self.delay_timer;
 
         self.increase_pc();
+        Ok(())
     }
 
     /// FX0A 	KeyOp 	Vx = get_key() 	A key press is awaited, and then stored in VX.
     /// (Blocking Operation. All instruction halted until next key event)
-    pub fn _fx0a_opcode(&mut self) {
+    /// Like the COSMAC VIP, the key counts once it is released again.
+    pub fn _fx0a_opcode(&mut self) -> Result<(), EmuError> {
         info!("FX0A: 0x{:X}", self.opcode);
-        info!("Implement Blocking Operation");
-        self.increase_pc();
+        let x = ((self.opcode & 0x0F00) >> 8) as usize;
+
+        // Leaving the PC alone re-runs this opcode next cycle until a key goes down and up
+        match self.key_wait {
+            Some(k) if self.key[k as usize] == 0 => {
+                self.key_wait = None;
+                self.v[x] = k;
+                self.increase_pc();
+                debug!("Key {:X} stored in V{:X}", k, x);
+            }
+            Some(_) => {}
+            None => self.key_wait = self.key.iter().position(|&k| k != 0).map(|k| k as u8),
+        }
+        Ok(())
     }
 
     /// FX15 	Timer 	delay_timer(Vx) 	Sets the delay timer to VX.
-    pub fn _fx15_opcode(&mut self) {
+    pub fn _fx15_opcode(&mut self) -> Result<(), EmuError> {
         info!("FX15: 0x{:X}", self.opcode);
 
         let mut reg = self.opcode & 0x0F00;
@@ -504,10 +1063,11 @@ impl<'c> Chipate<'c> {
         self.delay_timer = self.v[reg as usize];
 
         self.increase_pc();
+        Ok(())
     }
 
     /// FX18 	Sound 	sound_timer(Vx) 	Sets the sound timer to VX.
-    pub fn _fx18_opcode(&mut self) {
+    pub fn _fx18_opcode(&mut self) -> Result<(), EmuError> {
         info!("FX18: 0x{:X}", self.opcode);
 
         let mut reg = self.opcode & 0x0F00;
@@ -515,24 +1075,50 @@ impl<'c> Chipate<'c> {
         self.sound_timer = self.v[reg as usize];
 
         self.increase_pc();
+        Ok(())
     }
 
     /// FX1E 	MEM 	I +=Vx 	Adds VX to I.[3]
-    pub fn _fx1e_opcode(&mut self) {
+    pub fn _fx1e_opcode(&mut self) -> Result<(), EmuError> {
         info!("FX1E: 0x{:X}", self.opcode);
 
         let mut reg = self.opcode & 0x0F00;
         reg = reg >> 8;
-        self.i +=  self.v[reg as usize] as u16;
+        self.i = self.i.wrapping_add(self.v[reg as usize] as u16);
 
         self.increase_pc();
+        Ok(())
     }
 
     /// FX29 	MEM 	I=sprite_addr[Vx] 	Sets I to the location of the sprite for the character in VX. Characters 0-F (in hexadecimal) are represented by a 4x5 font.
-    pub fn _fx29_opcode(&mut self) {
+    pub fn _fx29_opcode(&mut self) -> Result<(), EmuError> {
         info!("FX29: 0x{:X}", self.opcode);
-        info!("TODO: Display Function");
+
+        let mut reg = self.opcode & 0x0F00;
+        reg = reg >> 8;
+
+        // The font lives at 0x000 and every character is 5 bytes tall
+        self.i = (self.v[reg as usize] & 0x0F) as u16 * 5;
+
         self.increase_pc();
+        debug!("Set I to sprite for {:X}: 0x{:X}", self.v[reg as usize], self.i);
+        Ok(())
+    }
+
+    /// FX30 	MEM 	I=big_sprite_addr[Vx] 	Sets I to the location of the 8x10 sprite for the
+    /// character in VX. (SUPER-CHIP)
+    pub fn _fx30_opcode(&mut self) -> Result<(), EmuError> {
+        info!("FX30: 0x{:X}", self.opcode);
+
+        let mut reg = self.opcode & 0x0F00;
+        reg = reg >> 8;
+
+        // The big font follows the small one and every character is 10 bytes tall
+        self.i = BIG_FONT_ADDR + (self.v[reg as usize] & 0x0F) as u16 * 10;
+
+        self.increase_pc();
+        debug!("Set I to big sprite for {:X}: 0x{:X}", self.v[reg as usize], self.i);
+        Ok(())
     }
 
     /// FX33 	BCD 	....  Stores the binary-coded decimal representation of VX, with the most
@@ -545,52 +1131,118 @@ impl<'c> Chipate<'c> {
     /// RAM[I+1] = ((V[((opcode&0x0F00)>>8)]/10)%10);
     /// RAM[I+2] = ((V[((opcode&0x0F00)>>8)]%100)%10);
     /// PC+=2;
-    pub fn _fx33_opcode(&mut self) {
+    pub fn _fx33_opcode(&mut self) -> Result<(), EmuError> {
         info !("FX33: 0x{:X}", self.opcode);
 
         let mut reg = self.opcode & 0x0F00;
         reg = reg >> 8;
 
+        self.check_memory(self.i as usize, 3)?;
+
         self.memory[self.i as usize] = self.v[reg as usize] / 100;
         debug!("BCD: {}", self.memory[self.i as usize]);
         self.memory[(self.i + 1) as usize] = self.v[reg as usize] / 10 % 10;
         debug!("BCD: {}", self.memory[(self.i + 1) as usize]);
-        self.memory[(self.i + 2) as usize] = self.v[reg as usize] / 10 % 10;
+        self.memory[(self.i + 2) as usize] = self.v[reg as usize] % 10;
         debug!("BCD: {}", self.memory[(self.i + 2) as usize]);
 
         self.increase_pc();
+        Ok(())
     }
 
     /// FX55 	MEM 	reg_dump(Vx,&I) 	Stores V0 to VX (including VX) in memory starting at address I.[4]
-    pub fn _fx55_opcode(&mut self) {
+    pub fn _fx55_opcode(&mut self) -> Result<(), EmuError> {
         info!("FX55: 0x{:X}", self.opcode);
+
+        let x = ((self.opcode & 0x0F00) >> 8) as usize;
+        let from = self.i as usize;
+        self.check_memory(from, x + 1)?;
+
+        self.memory[from..from + x + 1].copy_from_slice(&self.v[0..x + 1]);
+        if self.quirks.increment_i {
+            self.i = self.i.wrapping_add(x as u16 + 1);
+        }
+
         self.increase_pc();
+        Ok(())
     }
 
     /// FX65 	MEM 	reg_load(Vx,&I) 	Fills V0 to VX (including VX) with values from memory
     /// starting at address I.[4]
-    pub fn _fx65_opcode(&mut self) {
+    pub fn _fx65_opcode(&mut self) -> Result<(), EmuError> {
         info!("FX65: 0x{:X}", self.opcode);
+
+        let x = ((self.opcode & 0x0F00) >> 8) as usize;
+        let from = self.i as usize;
+        self.check_memory(from, x + 1)?;
+
+        self.v[0..x + 1].copy_from_slice(&self.memory[from..from + x + 1]);
+        if self.quirks.increment_i {
+            self.i = self.i.wrapping_add(x as u16 + 1);
+        }
+
+        self.increase_pc();
+        Ok(())
+    }
+
+    /// FX3A 	Sound 	pitch(Vx) 	Sets the audio pattern playback pitch to VX. (XO-CHIP)
+    pub fn _fx3a_opcode(&mut self) -> Result<(), EmuError> {
+        info!("FX3A: 0x{:X}", self.opcode);
+
+        let mut reg = self.opcode & 0x0F00;
+        reg = reg >> 8;
+        self.pitch = self.v[reg as usize];
+        self.pattern_flag = true;
+
+        self.increase_pc();
+        Ok(())
+    }
+
+    /// FX75 	MEM 	rpl_dump(Vx) 	Stores V0 to VX (including VX) in the RPL user flags. (SUPER-CHIP)
+    pub fn _fx75_opcode(&mut self) -> Result<(), EmuError> {
+        info!("FX75: 0x{:X}", self.opcode);
+
+        let x = ((self.opcode & 0x0F00) >> 8) as usize;
+        self.rpl[0..x + 1].copy_from_slice(&self.v[0..x + 1]);
+
+        self.increase_pc();
+        Ok(())
+    }
+
+    /// FX85 	MEM 	rpl_load(Vx) 	Fills V0 to VX (including VX) from the RPL user flags. (SUPER-CHIP)
+    pub fn _fx85_opcode(&mut self) -> Result<(), EmuError> {
+        info!("FX85: 0x{:X}", self.opcode);
+
+        let x = ((self.opcode & 0x0F00) >> 8) as usize;
+        self.v[0..x + 1].copy_from_slice(&self.rpl[0..x + 1]);
+
         self.increase_pc();
+        Ok(())
     }
 
-    pub fn _f_opcodes(&mut self) {
+    pub fn _f_opcodes(&mut self) -> Result<(), EmuError> {
         let sub_op = self.opcode & 0x00FF;
 
         match sub_op{
+            0x0000 if self.opcode == 0xF000 => self._f000_opcode(),
+            0x0001 => self._fn01_opcode(),
+            0x0002 if self.opcode == 0xF002 => self._f002_opcode(),
             0x0007 => self._fx07_opcode(),
             0x000a => self._fx0a_opcode(),
             0x0015 => self._fx15_opcode(),
             0x0018 => self._fx18_opcode(),
             0x001e => self._fx1e_opcode(),
             0x0029 => self._fx29_opcode(),
+            0x0030 => self._fx30_opcode(),
             0x0033 => self._fx33_opcode(),
+            0x003a => self._fx3a_opcode(),
             0x0055 => self._fx55_opcode(),
             0x0065 => self._fx65_opcode(),
+            0x0075 => self._fx75_opcode(),
+            0x0085 => self._fx85_opcode(),
             _ => {
-                // Using the catch all as a NOOP
                 info!("Catch all 0xFxxx: 0x{:X}", self.opcode);
-                self.increase_pc();
+                Err(self.invalid_opcode())
             }
         }
     }
@@ -600,7 +1252,7 @@ impl<'c> Chipate<'c> {
 
         let mut chip = Chipate {
             opcode: 0,
-            memory: [0; 4096],
+            memory: vec![0; 0x1000],
             v: [0; 16],
             i: 0,
             pc: 0,
@@ -610,15 +1262,34 @@ impl<'c> Chipate<'c> {
             stack: Vec::new(),
             // sp: 0,
             key: [0; 16],
+            key_wait: None,
             program: "",
-            clock_speed: 10,
+            load_address: 0x200,
+            quirks: Quirks::default(),
+            rpl: [0; 16],
+            exited: false,
+            xochip: false,
+            pattern: [0; 16],
+            pitch: 64,
+            pattern_flag: false,
+            rng: Box::new(SeededRng::new(rng::random_seed())),
+            random_log: None,
+            random_replay: VecDeque::new(),
         };
 
         for i in 0..80 { chip.memory[i] = FONTSET[i]; }
+        let big = BIG_FONT_ADDR as usize;
+        chip.memory[big..big + 160].copy_from_slice(&BIG_FONTSET);
         chip
     }
 }
 
+impl<'c> Default for Chipate<'c> {
+    fn default() -> Chipate<'c> {
+        Chipate::new()
+    }
+}
+
 static FONTSET: [u8; 80] = [0xF0, 0x90, 0x90, 0x90, 0xF0, 0x20, 0x60, 0x20, 0x20, 0x70,
                             0xF0, 0x10, 0xF0, 0x80, 0xF0, 0xF0, 0x10, 0xF0, 0x10, 0xF0,
                             0x90, 0x90, 0xF0, 0x10, 0x10, 0xF0, 0x80, 0xF0, 0x10, 0xF0,
@@ -627,3 +1298,34 @@ static FONTSET: [u8; 80] = [0xF0, 0x90, 0x90, 0x90, 0xF0, 0x20, 0x60, 0x20, 0x20
                             0xF0, 0x90, 0xF0, 0x90, 0x90, 0xE0, 0x90, 0xE0, 0x90, 0xE0,
                             0xF0, 0x80, 0x80, 0x80, 0xF0, 0xE0, 0x90, 0x90, 0x90, 0xE0,
                             0xF0, 0x80, 0xF0, 0x80, 0xF0, 0xF0, 0x80, 0xF0, 0x80, 0x80];
+
+/// Registers touched by 5XY2/5XY3, walking backwards when X is bigger than Y.
+fn register_range(x: usize, y: usize) -> Vec<usize> {
+    if x <= y {
+        (x..y + 1).collect()
+    } else {
+        (y..x + 1).rev().collect()
+    }
+}
+
+// Nesting depth of subroutine calls
+const STACK_SIZE: usize = 16;
+
+static BIG_FONT_ADDR: u16 = 0x50;
+
+static BIG_FONTSET: [u8; 160] = [0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C,
+                                 0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C,
+                                 0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF,
+                                 0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C,
+                                 0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06,
+                                 0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C,
+                                 0x3E, 0x7C, 0xE0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C,
+                                 0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60,
+                                 0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C,
+                                 0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C,
+                                 0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3,
+                                 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC,
+                                 0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C,
+                                 0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC,
+                                 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF,
+                                 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0];
@@ -0,0 +1,383 @@
+//! Debugger Module
+//! Command line debugger driving a `Chipate`. The frontend keeps running
+//! frames and asks `should_break` before every instruction, once that says
+//! stop it hands control to `prompt` until the user resumes.
+
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, Write};
+
+use chipate::Chipate;
+use watch::{self, Access, Compare, Target, Watchpoint};
+
+static HELP: &str = "\
+Numbers are hex, with or without a 0x prefix. An empty line repeats the last command.
+  s, step [N]         Execute N instructions (default 1)
+  c, continue         Run until a breakpoint
+  u, until ADDR       Run until the PC reaches ADDR
+  b, break ADDR       Set a breakpoint
+  d, delete ADDR      Remove a breakpoint
+  w, watch T [OP V]   Stop after T is written, T is VX, I, DT, ST or an address
+  rwatch T [OP V]     Stop after T is read
+  awatch T [OP V]     Stop after T is read or written
+                      OP V only stops while the new value compares true, e.g. watch V3 == 10
+  unwatch N           Remove watchpoint N
+  l, list             List breakpoints and watchpoints
+  r, regs             Show registers, timers and the stack
+  x, mem ADDR [LEN]   Hexdump LEN bytes of memory (default 40)
+  set VX|I VALUE      Change a V register or I
+  q, quit             Quit the emulator
+  h, help             Show this help";
+
+pub struct Debugger {
+    breakpoints: BTreeSet<u16>,
+
+    // Stopped at the prompt
+    paused: bool,
+
+    // Temporary breakpoint set by `until`
+    run_to: Option<u16>,
+
+    // Resuming from a breakpoint must not hit it again straight away
+    resume_from: Option<u16>,
+
+    watchpoints: Vec<Watchpoint>,
+
+    // Address and accesses of the instruction running now, checked after it
+    // ran, and the delay and sound timers ahead of it
+    pending: Option<(u16, Vec<Access>, (u8, u8))>,
+
+    // Where it stopped, and why, still needs printing
+    announce: bool,
+    hit: Option<String>,
+
+    last_command: String,
+    quit: bool,
+}
+
+impl Debugger {
+    /// Starts out paused, before the first instruction.
+    pub fn new() -> Debugger {
+        Debugger {
+            breakpoints: BTreeSet::new(),
+            paused: true,
+            run_to: None,
+            resume_from: None,
+            watchpoints: Vec::new(),
+            pending: None,
+            announce: true,
+            hit: None,
+            last_command: String::new(),
+            quit: false,
+        }
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn quit_requested(&self) -> bool {
+        self.quit
+    }
+
+    pub fn add_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.insert(addr);
+    }
+
+    pub fn remove_breakpoint(&mut self, addr: u16) -> bool {
+        self.breakpoints.remove(&addr)
+    }
+
+    pub fn breakpoints(&self) -> Vec<u16> {
+        self.breakpoints.iter().cloned().collect()
+    }
+
+    pub fn add_watchpoint(&mut self, w: Watchpoint) {
+        self.watchpoints.push(w);
+    }
+
+    pub fn remove_watchpoint(&mut self, n: usize) -> Option<Watchpoint> {
+        if n < self.watchpoints.len() {
+            Some(self.watchpoints.remove(n))
+        } else {
+            None
+        }
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    /// Stops the program from the outside, the next `should_break` says yes.
+    pub fn pause(&mut self) {
+        self.paused = true;
+        self.announce = true;
+        self.pending = None;
+    }
+
+    /// Checked before every instruction, pauses on watchpoints set off by the
+    /// previous instruction, on breakpoints and on `until` targets.
+    pub fn should_break(&mut self, chip: &Chipate) -> bool {
+        if self.paused {
+            return true;
+        }
+
+        if let Some(hit) = self.check_watchpoints(chip) {
+            self.hit = Some(hit);
+            self.pause();
+            return true;
+        }
+
+        let pc = chip.pc();
+        let resuming = self.resume_from.take() == Some(pc);
+        if !resuming && (self.run_to == Some(pc) || self.breakpoints.contains(&pc)) {
+            self.run_to = None;
+            self.pause();
+            return true;
+        }
+
+        self.watch_next(chip);
+        false
+    }
+
+    /// Remembers what the instruction at the PC is about to touch.
+    fn watch_next(&mut self, chip: &Chipate) {
+        if !self.watchpoints.is_empty() {
+            let timers = (chip.delay_timer(), chip.sound_timer());
+            self.pending = Some((chip.pc(), watch::accesses(chip), timers));
+        }
+    }
+
+    /// Describes the first watchpoint set off by the instruction that just
+    /// ran, or by the timers counting down after it.
+    fn check_watchpoints(&mut self, chip: &Chipate) -> Option<String> {
+        let (pc, accesses, timers) = self.pending.take()?;
+        let ticks: Vec<Access> = watch::countdown(timers, chip)
+            .into_iter()
+            .filter(|t| !accesses.iter().any(|a| a.write && a.target == t.target))
+            .collect();
+
+        for (n, w) in self.watchpoints.iter().enumerate() {
+            let value = || w.target.value(chip).map_or("?".to_string(), |v| format!("0x{:X}", v));
+            if let Some(a) = accesses.iter().find(|a| w.triggered_by(a, chip)) {
+                return Some(format!("Watchpoint {}: {} {} by 0x{:03X}, now {}",
+                                    n + 1,
+                                    w.target,
+                                    if a.write { "written" } else { "read" },
+                                    pc,
+                                    value()));
+            }
+            if ticks.iter().any(|a| w.triggered_by(a, chip)) {
+                return Some(format!("Watchpoint {}: {} counted down, now {}", n + 1, w.target, value()));
+            }
+        }
+        None
+    }
+
+    /// Reads and runs commands until one resumes the program, quits, or
+    /// steps it, so the frontend gets to redraw the screen in between.
+    pub fn prompt<R: BufRead, W: Write>(&mut self,
+                                        chip: &mut Chipate,
+                                        input: &mut R,
+                                        out: &mut W)
+                                        -> io::Result<()> {
+        if self.announce {
+            if let Some(hit) = self.hit.take() {
+                writeln!(out, "{}", hit)?;
+            }
+            writeln!(out, "{}", location(chip))?;
+            self.announce = false;
+        }
+
+        while self.paused && !self.quit {
+            write!(out, "(chipate) ")?;
+            out.flush()?;
+
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                // End of input, nobody left to ask
+                self.quit = true;
+                break;
+            }
+
+            let line = match line.trim() {
+                "" => self.last_command.clone(),
+                l => l.to_string(),
+            };
+            self.last_command = line.clone();
+
+            let reply = self.execute(chip, &line);
+            writeln!(out, "{}", reply.unwrap_or_else(|e| e))?;
+
+            if is_step(&line) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs a single command, returning what to show the user.
+    pub fn execute(&mut self, chip: &mut Chipate, line: &str) -> Result<String, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let arg = |n: usize| -> Result<u16, String> {
+            let w = words.get(n).ok_or_else(|| format!("{} needs an argument", words[0]))?;
+            parse_hex(w)
+        };
+
+        match words.first().cloned().unwrap_or("") {
+            "s" | "step" => {
+                let n = if words.len() > 1 { arg(1)? } else { 1 };
+                for _ in 0..n {
+                    if chip.exited() {
+                        break;
+                    }
+                    self.watch_next(chip);
+                    chip.emulate_cycle().map_err(|e| e.to_string())?;
+                    if let Some(hit) = self.check_watchpoints(chip) {
+                        return Ok(format!("{}\n{}", hit, location(chip)));
+                    }
+                }
+                Ok(location(chip))
+            }
+            "c" | "continue" => {
+                self.resume(chip);
+                Ok("Continuing".to_string())
+            }
+            "u" | "until" => {
+                let addr = arg(1)?;
+                self.run_to = Some(addr);
+                self.resume(chip);
+                Ok(format!("Running until 0x{:03X}", addr))
+            }
+            "b" | "break" => {
+                let addr = arg(1)?;
+                self.add_breakpoint(addr);
+                Ok(format!("Breakpoint at 0x{:03X}", addr))
+            }
+            "d" | "delete" => {
+                let addr = arg(1)?;
+                if self.remove_breakpoint(addr) {
+                    Ok(format!("Removed breakpoint at 0x{:03X}", addr))
+                } else {
+                    Err(format!("No breakpoint at 0x{:03X}", addr))
+                }
+            }
+            "w" | "watch" | "rwatch" | "awatch" => {
+                let target = Target::parse(words.get(1).ok_or("watch needs a target")?)?;
+                let mut w = match words[0] {
+                    "rwatch" => Watchpoint::read(target),
+                    "awatch" => Watchpoint::access(target),
+                    _ => Watchpoint::write(target),
+                };
+                if let Some(op) = words.get(2) {
+                    let op = Compare::parse(op).ok_or_else(|| format!("Unknown comparison {}", op))?;
+                    w = w.when(op, arg(3)?);
+                }
+                self.add_watchpoint(w);
+                Ok(format!("Watchpoint {}: {}", self.watchpoints.len(), w))
+            }
+            "unwatch" => {
+                let n = arg(1)? as usize;
+                match self.remove_watchpoint(n.wrapping_sub(1)) {
+                    Some(w) => Ok(format!("Removed watchpoint {}: {}", n, w)),
+                    None => Err(format!("No watchpoint {}", n)),
+                }
+            }
+            "l" | "list" => {
+                if self.breakpoints.is_empty() && self.watchpoints.is_empty() {
+                    return Ok("No breakpoints or watchpoints".to_string());
+                }
+                let breaks = self.breakpoints.iter().map(|b| format!("Breakpoint at 0x{:03X}", b));
+                let watches = self.watchpoints.iter().enumerate().map(|(n, w)| format!("Watchpoint {}: {}", n + 1, w));
+                Ok(breaks.chain(watches).collect::<Vec<String>>().join("\n"))
+            }
+            "r" | "regs" => Ok(registers(chip)),
+            "x" | "mem" => {
+                let addr = arg(1)? as usize;
+                let len = if words.len() > 2 { arg(2)? as usize } else { 0x40 };
+                Ok(hexdump(chip.memory(), addr, len))
+            }
+            "set" => {
+                let target = words.get(1).ok_or("set needs a register and a value")?.to_uppercase();
+                let value = arg(2)?;
+                if target == "I" {
+                    chip.set_i(value);
+                } else if target.starts_with('V') && target.len() == 2 {
+                    let x = parse_hex(&target[1..])? as usize;
+                    chip.set_v(x, value as u8);
+                } else {
+                    return Err(format!("Unknown register {}", target));
+                }
+                Ok(registers(chip))
+            }
+            "q" | "quit" => {
+                self.quit = true;
+                Ok("Bye".to_string())
+            }
+            "h" | "help" => Ok(HELP.to_string()),
+            w => Err(format!("Unknown command {}, try help", w)),
+        }
+    }
+
+    fn resume(&mut self, chip: &Chipate) {
+        self.paused = false;
+        self.resume_from = Some(chip.pc());
+    }
+}
+
+impl Default for Debugger {
+    fn default() -> Debugger {
+        Debugger::new()
+    }
+}
+
+fn is_step(line: &str) -> bool {
+    matches!(line.split_whitespace().next(), Some("s") | Some("step"))
+}
+
+/// Parses a hex number with an optional 0x prefix.
+fn parse_hex(s: &str) -> Result<u16, String> {
+    let digits = s.trim_start_matches("0x").trim_start_matches("0X");
+    u16::from_str_radix(digits, 16).map_err(|_| format!("Invalid number {}", s))
+}
+
+/// The instruction about to run.
+fn location(chip: &Chipate) -> String {
+    let pc = chip.pc() as usize;
+    let mem = chip.memory();
+    match (mem.get(pc), mem.get(pc + 1)) {
+        (Some(&a), Some(&b)) => format!("0x{:03X}: {:02X}{:02X}", pc, a, b),
+        _ => format!("0x{:03X}: out of memory", pc),
+    }
+}
+
+fn registers(chip: &Chipate) -> String {
+    let v: Vec<String> = chip.v().iter().enumerate().map(|(x, v)| format!("V{:X}={:02X}", x, v)).collect();
+    let stack: Vec<String> = chip.stack().iter().map(|a| format!("{:03X}", a)).collect();
+
+    format!("{}\n{}\nPC={:03X} I={:03X} DT={:02X} ST={:02X}\nStack: [{}]",
+            v[..8].join(" "),
+            v[8..].join(" "),
+            chip.pc(),
+            chip.i(),
+            chip.delay_timer(),
+            chip.sound_timer(),
+            stack.join(" "))
+}
+
+/// Sixteen bytes per line, with the address in front.
+fn hexdump(memory: &[u8], addr: usize, len: usize) -> String {
+    let end = (addr + len).min(memory.len());
+    if addr >= end {
+        return format!("0x{:03X} is out of memory", addr);
+    }
+
+    let lines: Vec<String> = memory[addr..end]
+        .chunks(16)
+        .enumerate()
+        .map(|(n, row)| {
+            let bytes: Vec<String> = row.iter().map(|b| format!("{:02X}", b)).collect();
+            format!("{:04X}: {}", addr + n * 16, bytes.join(" "))
+        })
+        .collect();
+    lines.join("\n")
+}
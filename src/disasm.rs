@@ -0,0 +1,299 @@
+//! Disassembler Module
+//! Turns opcodes back into Octo assembly. `disassemble` handles a single
+//! opcode, `Listing` follows the control flow of a whole ROM to tell code
+//! from sprite data and names every jump, call and I target.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+/// Octo mnemonic for `opcode`, with addresses written out in hex. `long` is
+/// the word following F000. None for opcodes no interpreter knows.
+pub fn disassemble(opcode: u16, long: Option<u16>) -> Option<String> {
+    format(opcode, long, &|addr| format!("0x{:03X}", addr))
+}
+
+fn format(opcode: u16, long: Option<u16>, name: &dyn Fn(u16) -> String) -> Option<String> {
+    let x = (opcode & 0x0F00) >> 8;
+    let y = (opcode & 0x00F0) >> 4;
+    let n = opcode & 0x000F;
+    let nn = opcode & 0x00FF;
+    let nnn = opcode & 0x0FFF;
+
+    let s = match opcode & 0xF000 {
+        0x0000 => match opcode {
+            0x00E0 => "clear".to_string(),
+            0x00EE => "return".to_string(),
+            0x00FB => "scroll-right".to_string(),
+            0x00FC => "scroll-left".to_string(),
+            0x00FD => "exit".to_string(),
+            0x00FE => "lores".to_string(),
+            0x00FF => "hires".to_string(),
+            _ if opcode & 0xFFF0 == 0x00C0 => format!("scroll-down {}", n),
+            _ if opcode & 0xFFF0 == 0x00D0 => format!("scroll-up {}", n),
+            _ => return None,
+        },
+        0x1000 => format!("jump {}", name(nnn)),
+        0x2000 => format!(":call {}", name(nnn)),
+        0x3000 => format!("if v{:x} != 0x{:02X} then", x, nn),
+        0x4000 => format!("if v{:x} == 0x{:02X} then", x, nn),
+        0x5000 => match n {
+            0x0 => format!("if v{:x} != v{:x} then", x, y),
+            0x2 => format!("save v{:x} - v{:x}", x, y),
+            0x3 => format!("load v{:x} - v{:x}", x, y),
+            _ => return None,
+        },
+        0x6000 => format!("v{:x} := 0x{:02X}", x, nn),
+        0x7000 => format!("v{:x} += 0x{:02X}", x, nn),
+        0x8000 => {
+            let op = match n {
+                0x0 => ":=",
+                0x1 => "|=",
+                0x2 => "&=",
+                0x3 => "^=",
+                0x4 => "+=",
+                0x5 => "-=",
+                0x6 => ">>=",
+                0x7 => "=-",
+                0xE => "<<=",
+                _ => return None,
+            };
+            format!("v{:x} {} v{:x}", x, op, y)
+        }
+        0x9000 if n == 0 => format!("if v{:x} == v{:x} then", x, y),
+        0xA000 => format!("i := {}", name(nnn)),
+        0xB000 => format!("jump0 {}", name(nnn)),
+        0xC000 => format!("v{:x} := random 0x{:02X}", x, nn),
+        0xD000 => format!("sprite v{:x} v{:x} {}", x, y, n),
+        0xE000 => match nn {
+            0x9E => format!("if v{:x} -key then", x),
+            0xA1 => format!("if v{:x} key then", x),
+            _ => return None,
+        },
+        0xF000 => match opcode {
+            0xF000 => format!("i := long {}", name(long?)),
+            0xF002 => "audio".to_string(),
+            _ => match nn {
+                0x01 => format!("plane {}", x),
+                0x07 => format!("v{:x} := delay", x),
+                0x0A => format!("v{:x} := key", x),
+                0x15 => format!("delay := v{:x}", x),
+                0x18 => format!("buzzer := v{:x}", x),
+                0x1E => format!("i += v{:x}", x),
+                0x29 => format!("i := hex v{:x}", x),
+                0x30 => format!("i := bighex v{:x}", x),
+                0x33 => format!("bcd v{:x}", x),
+                0x3A => format!("pitch := v{:x}", x),
+                0x55 => format!("save v{:x}", x),
+                0x65 => format!("load v{:x}", x),
+                0x75 => format!("saveflags v{:x}", x),
+                0x85 => format!("loadflags v{:x}", x),
+                _ => return None,
+            },
+        },
+        _ => return None,
+    };
+    Some(s)
+}
+
+/// How long the instruction is, F000 NNNN takes four bytes.
+fn length(opcode: u16) -> u16 {
+    if opcode == 0xF000 { 4 } else { 2 }
+}
+
+/// Whether the instruction skips the next one on some condition.
+fn is_skip(opcode: u16) -> bool {
+    match opcode & 0xF000 {
+        0x3000 | 0x4000 => true,
+        0x5000 | 0x9000 => opcode & 0x000F == 0,
+        0xE000 => opcode & 0x00FF == 0x9E || opcode & 0x00FF == 0xA1,
+        _ => false,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Label {
+    Main,
+    Jump,
+    Call,
+    Data,
+}
+
+/// A ROM split into code and data by following jumps, calls and skips from
+/// the entry point. Bytes no path reaches are treated as data.
+pub struct Listing<'r> {
+    rom: &'r [u8],
+    origin: u16,
+
+    // Addresses of the first byte of every instruction reached
+    code: BTreeSet<u16>,
+    labels: BTreeMap<u16, Label>,
+}
+
+impl<'r> Listing<'r> {
+    /// Analyses `rom`, loaded at and entered from `origin`.
+    pub fn new(rom: &'r [u8], origin: u16) -> Listing<'r> {
+        let mut listing = Listing {
+            rom,
+            origin,
+            code: BTreeSet::new(),
+            labels: BTreeMap::new(),
+        };
+        listing.labels.insert(origin, Label::Main);
+        listing.trace(origin);
+        listing
+    }
+
+    fn word(&self, addr: u16) -> Option<u16> {
+        let at = addr.checked_sub(self.origin)? as usize;
+        match (self.rom.get(at), self.rom.get(at + 1)) {
+            (Some(&a), Some(&b)) => Some((a as u16) << 8 | b as u16),
+            _ => None,
+        }
+    }
+
+    fn label(&mut self, addr: u16, kind: Label) {
+        let entry = self.labels.entry(addr).or_insert(kind);
+        // A call target is worth naming as a subroutine even if something jumps there too
+        if kind == Label::Call && *entry == Label::Jump {
+            *entry = kind;
+        }
+    }
+
+    /// Recursive descent from `entry`, with a work list instead of recursion.
+    fn trace(&mut self, entry: u16) {
+        let mut todo = vec![entry];
+
+        while let Some(addr) = todo.pop() {
+            if self.code.contains(&addr) {
+                continue;
+            }
+            let opcode = match self.word(addr) {
+                Some(op) => op,
+                None => continue,
+            };
+            let long = self.word(addr.wrapping_add(2));
+            if format(opcode, long, &|_| String::new()).is_none() {
+                debug!("Stopped at invalid opcode 0x{:04X} at 0x{:03X}", opcode, addr);
+                continue;
+            }
+            self.code.insert(addr);
+
+            let next = addr.wrapping_add(length(opcode));
+            let nnn = opcode & 0x0FFF;
+            match opcode & 0xF000 {
+                0x0000 if opcode == 0x00EE || opcode == 0x00FD => {}
+                0x1000 => {
+                    self.label(nnn, Label::Jump);
+                    todo.push(nnn);
+                }
+                0x2000 => {
+                    self.label(nnn, Label::Call);
+                    todo.push(nnn);
+                    todo.push(next);
+                }
+                // The offset in V0 is unknown, so only the table start is followed
+                0xB000 => {
+                    self.label(nnn, Label::Jump);
+                    todo.push(nnn);
+                }
+                0xA000 => {
+                    self.label(nnn, Label::Data);
+                    todo.push(next);
+                }
+                0xF000 if opcode == 0xF000 => {
+                    if let Some(l) = long {
+                        self.label(l, Label::Data);
+                    }
+                    todo.push(next);
+                }
+                _ if is_skip(opcode) => {
+                    let skipped = self.word(next).map_or(2, length);
+                    todo.push(next);
+                    todo.push(next.wrapping_add(skipped));
+                }
+                _ => todo.push(next),
+            }
+        }
+    }
+
+    /// Whether the instruction at `addr` was reached.
+    pub fn is_code(&self, addr: u16) -> bool {
+        self.code.contains(&addr)
+    }
+
+    fn name(&self, addr: u16) -> String {
+        match self.labels.get(&addr) {
+            Some(&Label::Main) => "main".to_string(),
+            Some(&Label::Jump) => format!("label_{:03X}", addr),
+            Some(&Label::Call) => format!("sub_{:03X}", addr),
+            Some(&Label::Data) => format!("data_{:03X}", addr),
+            None => format!("0x{:03X}", addr),
+        }
+    }
+
+    /// Octo source for the whole ROM. Every line carries its address as a
+    /// comment, instructions their raw opcode too. Labels pointing outside the ROM or into the
+    /// middle of an instruction stay plain addresses.
+    pub fn to_octo(&self) -> String {
+        let mut out = String::new();
+        let end = self.origin as usize + self.rom.len();
+        let mut addr = self.origin as usize;
+        let name = |a: u16| if self.is_labelled(a) { self.name(a) } else { format!("0x{:03X}", a) };
+
+        while addr < end {
+            let a = addr as u16;
+            if self.is_labelled(a) {
+                if addr > self.origin as usize {
+                    out.push('\n');
+                }
+                let _ = writeln!(out, ": {}", self.name(a));
+            }
+
+            if self.code.contains(&a) {
+                let opcode = self.word(a).unwrap_or(0);
+                let long = self.word(a.wrapping_add(2));
+                let text = format(opcode, long, &name).unwrap_or_default();
+                let len = length(opcode);
+                let raw = match (opcode, long) {
+                    (0xF000, Some(l)) => format!("{:04X} {:04X}", opcode, l),
+                    _ => format!("{:04X}", opcode),
+                };
+                let _ = writeln!(out, "\t{:<28}# 0x{:03X}  {}", text, a, raw);
+                addr += len as usize;
+                continue;
+            }
+
+            // Data runs until the next instruction or label, eight bytes a line
+            let mut bytes = Vec::new();
+            while addr < end && bytes.len() < 8 {
+                let a = addr as u16;
+                if !bytes.is_empty() && (self.code.contains(&a) || self.is_labelled(a)) {
+                    break;
+                }
+                bytes.push(self.rom[addr - self.origin as usize]);
+                addr += 1;
+            }
+            let text: Vec<String> = bytes.iter().map(|b| format!("0x{:02X}", b)).collect();
+            let _ = writeln!(out, "\t{:<28}# 0x{:03X}", text.join(" "), a);
+        }
+        out
+    }
+
+    /// Only labels starting an instruction or a data byte inside the ROM get printed.
+    fn is_labelled(&self, addr: u16) -> bool {
+        if !self.labels.contains_key(&addr) {
+            return false;
+        }
+        let end = self.origin as usize + self.rom.len();
+        if addr < self.origin || addr as usize >= end {
+            return false;
+        }
+        // Inside an instruction, e.g. the second byte of a two byte opcode
+        let inside = |back: u16| {
+            addr.checked_sub(back).is_some_and(|start| {
+                self.code.contains(&start) && self.word(start).map_or(2, length) > back
+            })
+        };
+        !(1..4).any(inside)
+    }
+}
@@ -1,49 +1,123 @@
-use sdl::video;
-use sdl::Rect;
+/// Largest framebuffer, used by the SUPER-CHIP high resolution mode.
+pub const WIDTH: usize = 128;
+pub const HEIGHT: usize = 64;
 
+/// The original Chip-8 resolution.
+pub const LORES_WIDTH: usize = 64;
+pub const LORES_HEIGHT: usize = 32;
+
+/// Display
+/// Framebuffer, 64x32 by default or 128x64 in high resolution mode.
+/// Only the top left `width()` x `height()` pixels are in use at any time.
+/// Every pixel holds one bit per XO-CHIP bitplane, so plain Chip-8 programs
+/// only ever see 0 and 1 while XO-CHIP ones get four colors.
+/// Rendering it is left to the frontend.
+#[derive(Clone)]
 pub struct Display {
-    gfx: [[u8; 64]; 32],
+    gfx: [[u8; WIDTH]; HEIGHT],
+    hires: bool,
+    planes: u8,
     draw_flag: bool,
-    screen: video::Surface,
 }
 
-static SCALE: isize = 20;
-
 impl Display {
     pub fn new() -> Display {
         Display {
-            gfx: [[0; 64]; 32],
+            gfx: [[0; WIDTH]; HEIGHT],
+            hires: false,
+            planes: 1,
             draw_flag: true,
-            screen: video::set_video_mode(64 * SCALE + 10,
-                                          32 * SCALE + 10,
-                                          8,
-                                          &[video::SurfaceFlag::HWSurface],
-                                          &[video::VideoFlag::DoubleBuf])
-                    .unwrap(),
         }
     }
 
+    /// Clears the selected planes.
     pub fn clear(&mut self) {
-        self.gfx = [[0; 64]; 32];
+        for row in self.gfx.iter_mut() {
+            for p in row.iter_mut() {
+                *p &= !self.planes;
+            }
+        }
+        self.draw_flag = true;
+    }
+
+    pub fn width(&self) -> usize {
+        if self.hires { WIDTH } else { LORES_WIDTH }
+    }
+
+    pub fn height(&self) -> usize {
+        if self.hires { HEIGHT } else { LORES_HEIGHT }
+    }
+
+    pub fn hires(&self) -> bool {
+        self.hires
+    }
+
+    /// Switches between 64x32 and 128x64, all planes are cleared either way.
+    pub fn set_hires(&mut self, hires: bool) {
+        self.hires = hires;
+        self.gfx = [[0; WIDTH]; HEIGHT];
         self.draw_flag = true;
     }
 
-    pub fn draw(&mut self, x: usize, y: usize, sprite: &[u8]) -> u8 {
+    /// Bitmask of the planes drawing, clearing and scrolling work on.
+    pub fn planes(&self) -> u8 {
+        self.planes
+    }
+
+    /// Selects the planes by bitmask, 1 and 2 being the two XO-CHIP planes.
+    pub fn select_planes(&mut self, planes: u8) {
+        self.planes = planes & 0x3;
+    }
+
+    /// Number of selected planes, a sprite holds this many copies of its data.
+    pub fn plane_count(&self) -> usize {
+        self.planes.count_ones() as usize
+    }
+
+    /// XORs an 8 pixel wide sprite onto the screen, returning 1 on collision.
+    /// The origin always wraps, with `clip` set the rest of the sprite is cut
+    /// off at the edges instead of wrapping around.
+    /// With several planes selected `sprite` holds the data for each of them
+    /// one after another.
+    pub fn draw(&mut self, x: usize, y: usize, sprite: &[u8], clip: bool) -> u8 {
+        self.draw_sprite(x, y, sprite, 1, clip)
+    }
+
+    /// Same as `draw` for the 16x16 SUPER-CHIP sprites, two bytes per row.
+    pub fn draw_wide(&mut self, x: usize, y: usize, sprite: &[u8], clip: bool) -> u8 {
+        self.draw_sprite(x, y, sprite, 2, clip)
+    }
+
+    fn draw_sprite(&mut self, x: usize, y: usize, sprite: &[u8], row_bytes: usize, clip: bool) -> u8 {
         let mut collision = 0u8;
-        let n = sprite.len() as usize;
+        let (w, h) = (self.width(), self.height());
+        let x = x % w;
+        let y = y % h;
         let mut yj: usize;
         let mut xi: usize;
 
-        for j in 0..n {
-            for i in 0..8 {
-                yj = (y + j) % 32;
-                xi = (x + i) % 64;
+        let rows = sprite.len() / row_bytes / self.plane_count().max(1);
+        if rows == 0 {
+            return 0;
+        }
+        let planes = self.planes;
+        let selected = (0..2).map(|b| 1u8 << b).filter(|p| planes & p != 0);
+
+        for (data, plane) in sprite.chunks(rows * row_bytes).zip(selected) {
+            for (j, row) in data.chunks(row_bytes).enumerate() {
+                for i in 0..row_bytes * 8 {
+                    if clip && (y + j >= h || x + i >= w) {
+                        continue;
+                    }
+                    yj = (y + j) % h;
+                    xi = (x + i) % w;
 
-                if (sprite[j] & (0x80 >> i)) != 0 {
-                    if self.gfx[yj][xi] == 1 {
-                        collision = 1
+                    if (row[i / 8] & (0x80 >> (i % 8))) != 0 {
+                        if self.gfx[yj][xi] & plane != 0 {
+                            collision = 1
+                        }
+                        self.gfx[yj][xi] ^= plane;
                     }
-                    self.gfx[yj][xi] ^= 1;
                 }
             }
         }
@@ -52,29 +126,72 @@ impl Display {
         collision
     }
 
-    pub fn draw_screen(&mut self) {
-        if !self.draw_flag {
-            return;
-        }
-        let mut pixel: u8;
-        let sc = SCALE as u16;
-        let pt = |p: usize| (p as i16) * (SCALE as i16);
-
-        for y in 0..32 {
-            for x in 0..64 {
-                pixel = if self.gfx[y][x] != 0 { 255 } else { 0 };
-                self.screen
-                    .fill_rect(Some(Rect {
-                                        x: pt(x),
-                                        y: pt(y),
-                                        w: sc,
-                                        h: sc,
-                                    }),
-                               video::RGB(pixel, pixel, pixel));
+    /// Moves the selected planes by `dx`, `dy` pixels, blank pixels come in behind.
+    fn scroll(&mut self, dx: isize, dy: isize) {
+        let (w, h) = (self.width() as isize, self.height() as isize);
+        let planes = self.planes;
+        let old = self.gfx;
+
+        for y in 0..h {
+            for x in 0..w {
+                let (sx, sy) = (x - dx, y - dy);
+                let src = if sx >= 0 && sx < w && sy >= 0 && sy < h {
+                    old[sy as usize][sx as usize]
+                } else {
+                    0
+                };
+                let p = &mut self.gfx[y as usize][x as usize];
+                *p = (*p & !planes) | (src & planes);
             }
         }
+        self.draw_flag = true;
+    }
+
+    /// Moves the picture down `n` pixels, blank rows come in at the top.
+    pub fn scroll_down(&mut self, n: usize) {
+        self.scroll(0, n as isize);
+    }
+
+    /// Moves the picture up `n` pixels, blank rows come in at the bottom.
+    pub fn scroll_up(&mut self, n: usize) {
+        self.scroll(0, -(n as isize));
+    }
+
+    /// Moves the picture right `n` pixels, blank columns come in on the left.
+    pub fn scroll_right(&mut self, n: usize) {
+        self.scroll(n as isize, 0);
+    }
 
-        self.screen.flip();
+    /// Moves the picture left `n` pixels, blank columns come in on the right.
+    pub fn scroll_left(&mut self, n: usize) {
+        self.scroll(-(n as isize), 0);
+    }
+
+    /// Raw pixel rows, bit 0 is the first plane and bit 1 the second.
+    pub fn gfx(&self) -> &[[u8; WIDTH]; HEIGHT] {
+        &self.gfx
+    }
+
+    /// Puts back a framebuffer taken from `gfx`, for save states.
+    pub fn restore(&mut self, gfx: [[u8; WIDTH]; HEIGHT], hires: bool, planes: u8) {
+        self.gfx = gfx;
+        self.hires = hires;
+        self.select_planes(planes);
+        self.draw_flag = true;
+    }
+
+    /// True when the framebuffer changed since the last `clear_draw_flag`.
+    pub fn draw_flag(&self) -> bool {
+        self.draw_flag
+    }
+
+    pub fn clear_draw_flag(&mut self) {
         self.draw_flag = false;
     }
 }
+
+impl Default for Display {
+    fn default() -> Display {
+        Display::new()
+    }
+}
@@ -0,0 +1,150 @@
+//! Dump Module
+//! Still images of a `Display`, for screenshots and tests without a window.
+//! Only the part of the framebuffer in use is written, one image pixel per
+//! Chip-8 pixel.
+
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use display::Display;
+use error::EmuError;
+
+/// Gray level for each combination of plane bits, the same as the SDL window.
+static SHADES: [u8; 4] = [0, 255, 170, 85];
+
+/// ASCII character for each combination of plane bits.
+static CHARS: [u8; 4] = [b'.', b'#', b'o', b'@'];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Png,
+    Pbm,
+    Ascii,
+}
+
+impl Format {
+    /// Picks the format from a file extension, anything unknown is ASCII.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Format {
+        let ext = path.as_ref().extension().map(|e| e.to_string_lossy().to_lowercase());
+        match ext.as_deref() {
+            Some("png") => Format::Png,
+            Some("pbm") => Format::Pbm,
+            _ => Format::Ascii,
+        }
+    }
+
+    pub fn encode(self, display: &Display) -> Vec<u8> {
+        match self {
+            Format::Png => png(display),
+            Format::Pbm => pbm(display),
+            Format::Ascii => ascii(display).into_bytes(),
+        }
+    }
+}
+
+/// Writes `display` to `path` in the format its extension asks for.
+pub fn save<P: AsRef<Path>>(display: &Display, path: P) -> Result<(), EmuError> {
+    let bytes = Format::from_path(&path).encode(display);
+    File::create(path)?.write_all(&bytes)?;
+    Ok(())
+}
+
+/// One line per row, `.` for off and `#` for on. XO-CHIP pixels lit only
+/// in the second plane are `o`, in both `@`.
+pub fn ascii(display: &Display) -> String {
+    let mut s = String::with_capacity((display.width() + 1) * display.height());
+    for row in rows(display) {
+        s.extend(row.iter().map(|&p| CHARS[(p & 0x3) as usize] as char));
+        s.push('\n');
+    }
+    s
+}
+
+/// Plain PBM, 1 for any lit pixel.
+pub fn pbm(display: &Display) -> Vec<u8> {
+    let mut b = format!("P1\n{} {}\n", display.width(), display.height()).into_bytes();
+    for row in rows(display) {
+        let bits: Vec<&str> = row.iter().map(|&p| if p & 0x3 != 0 { "1" } else { "0" }).collect();
+        b.extend_from_slice(bits.join(" ").as_bytes());
+        b.push(b'\n');
+    }
+    b
+}
+
+/// 8-bit grayscale PNG, stored without compression.
+pub fn png(display: &Display) -> Vec<u8> {
+    let mut raw = Vec::with_capacity((display.width() + 1) * display.height());
+    for row in rows(display) {
+        // Filter type 0, the row as is
+        raw.push(0);
+        raw.extend(row.iter().map(|&p| SHADES[(p & 0x3) as usize]));
+    }
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&be32(display.width() as u32));
+    header.extend_from_slice(&be32(display.height() as u32));
+    // Bit depth 8, grayscale, deflate, no filtering, no interlace
+    header.extend_from_slice(&[8, 0, 0, 0, 0]);
+
+    let mut b = b"\x89PNG\r\n\x1a\n".to_vec();
+    chunk(&mut b, b"IHDR", &header);
+    chunk(&mut b, b"IDAT", &zlib_stored(&raw));
+    chunk(&mut b, b"IEND", &[]);
+    b
+}
+
+fn rows(display: &Display) -> impl Iterator<Item = &[u8]> {
+    let w = display.width();
+    display.gfx().iter().take(display.height()).map(move |row| &row[..w])
+}
+
+fn chunk(b: &mut Vec<u8>, kind: &[u8], data: &[u8]) {
+    b.extend_from_slice(&be32(data.len() as u32));
+    let start = b.len();
+    b.extend_from_slice(kind);
+    b.extend_from_slice(data);
+    let crc = crc32(&b[start..]);
+    b.extend_from_slice(&be32(crc));
+}
+
+/// A zlib stream of stored deflate blocks, at most 65535 bytes each.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut z = vec![0x78, 0x01];
+    let mut blocks = data.chunks(0xffff).peekable();
+    if blocks.peek().is_none() {
+        z.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let len = block.len() as u16;
+        z.push(blocks.peek().is_none() as u8);
+        z.extend_from_slice(&[len as u8, (len >> 8) as u8, !len as u8, (!len >> 8) as u8]);
+        z.extend_from_slice(block);
+    }
+    z.extend_from_slice(&be32(adler32(data)));
+    z
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb88320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    b << 16 | a
+}
+
+fn be32(v: u32) -> [u8; 4] {
+    [(v >> 24) as u8, (v >> 16) as u8, (v >> 8) as u8, v as u8]
+}
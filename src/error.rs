@@ -0,0 +1,73 @@
+//! Error Module
+//! Everything that can stop the emulator. Faults raised while running a
+//! program carry the PC and opcode of the instruction that caused them.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum EmuError {
+    /// The ROM does not fit between the load address and the end of memory.
+    RomTooLarge { size: usize, max: usize },
+    /// 00EE with nothing on the stack.
+    StackUnderflow { pc: u16, opcode: u16 },
+    /// 2NNN with every stack level in use.
+    StackOverflow { pc: u16, opcode: u16 },
+    /// The PC ran off the end of memory.
+    PcOutOfBounds { pc: u16 },
+    /// The opcode does not decode to any instruction.
+    InvalidOpcode { pc: u16, opcode: u16 },
+    /// The instruction touched memory past the end, `addr` being the first bad address.
+    MemoryOutOfBounds { pc: u16, opcode: u16, addr: usize },
+    /// A save state that can not be loaded, with the reason.
+    InvalidState(String),
+    /// A movie that can not be loaded or does not fit the ROM, with the reason.
+    InvalidMovie(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for EmuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EmuError::RomTooLarge { size, max } => {
+                write!(f, "ROM is {} bytes, only {} fit in memory", size, max)
+            }
+            EmuError::StackUnderflow { pc, opcode } => {
+                write!(f, "Stack underflow at PC 0x{:03X} (opcode 0x{:04X})", pc, opcode)
+            }
+            EmuError::StackOverflow { pc, opcode } => {
+                write!(f, "Stack overflow at PC 0x{:03X} (opcode 0x{:04X})", pc, opcode)
+            }
+            EmuError::PcOutOfBounds { pc } => write!(f, "PC 0x{:03X} is out of memory", pc),
+            EmuError::InvalidOpcode { pc, opcode } => {
+                write!(f, "Invalid opcode 0x{:04X} at PC 0x{:03X}", opcode, pc)
+            }
+            EmuError::MemoryOutOfBounds { pc, opcode, addr } => {
+                write!(f,
+                       "Memory access at 0x{:X} is out of bounds, PC 0x{:03X} (opcode 0x{:04X})",
+                       addr,
+                       pc,
+                       opcode)
+            }
+            EmuError::InvalidState(ref reason) => write!(f, "Invalid save state: {}", reason),
+            EmuError::InvalidMovie(ref reason) => write!(f, "Invalid movie: {}", reason),
+            EmuError::Io(ref e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+impl Error for EmuError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            EmuError::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for EmuError {
+    fn from(e: io::Error) -> EmuError {
+        EmuError::Io(e)
+    }
+}
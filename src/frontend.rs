@@ -0,0 +1,24 @@
+//! Frontend Module
+//! Traits a frontend implements to plug into a `Chipate`. The core never
+//! knows whether it is talking to SDL, a terminal or a test double.
+
+use display::Display;
+
+/// Receives the framebuffer whenever it has changed.
+pub trait VideoSink {
+    fn render(&mut self, display: &Display);
+}
+
+/// Driven by the sound timer, the tone should play while `on` is true.
+pub trait AudioSink {
+    fn set_tone(&mut self, on: bool);
+
+    /// XO-CHIP programs replace the tone with a 1-bit, 128 sample pattern
+    /// played back at `4000 * 2 ^ ((pitch - 64) / 48)` Hz.
+    fn set_pattern(&mut self, _pattern: &[u8; 16], _pitch: u8) {}
+}
+
+/// Fills the keypad state, 1 for pressed and 0 for released.
+pub trait InputSource {
+    fn poll(&mut self, key: &mut [u8; 16]);
+}
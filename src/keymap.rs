@@ -0,0 +1,57 @@
+//! Keymap Module
+//! Which keyboard key stands for each of the 16 keypad keys. Frontends turn
+//! their key events into characters and look them up here.
+
+/// The hex keypad laid over the left hand side of a QWERTY keyboard:
+///
+/// ```text
+/// 1 2 3 C      1 2 3 4
+/// 4 5 6 D  ->  Q W E R
+/// 7 8 9 E      A S D F
+/// A 0 B F      Z X C V
+/// ```
+///
+/// Written as the keyboard key for keypad keys 0 to F in order.
+pub static QWERTY: &str = "x123qweasdzc4rfv";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Keymap {
+    keys: [char; 16],
+}
+
+impl Keymap {
+    /// Builds a keymap from 16 characters, the keyboard key for keypad keys 0 to F.
+    pub fn from_layout(layout: &str) -> Result<Keymap, String> {
+        let chars: Vec<char> = layout.chars().flat_map(|c| c.to_lowercase()).collect();
+        if chars.len() != 16 {
+            return Err(format!("Keymap {} needs 16 keys, one for each of 0 to F", layout));
+        }
+
+        let mut keys = [' '; 16];
+        for (k, &c) in chars.iter().enumerate() {
+            if keys[..k].contains(&c) {
+                return Err(format!("Keymap {} uses {} twice", layout, c));
+            }
+            keys[k] = c;
+        }
+
+        Ok(Keymap { keys })
+    }
+
+    /// The keypad key bound to keyboard key `c`.
+    pub fn keypad_key(&self, c: char) -> Option<usize> {
+        let c = c.to_lowercase().next().unwrap_or(c);
+        self.keys.iter().position(|&k| k == c)
+    }
+
+    /// The keyboard key bound to keypad key `k`.
+    pub fn keyboard_key(&self, k: usize) -> char {
+        self.keys[k & 0xF]
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        Keymap::from_layout(QWERTY).unwrap()
+    }
+}
@@ -0,0 +1,39 @@
+//! Chipate
+//! Headless Chip-8 emulator core. Frontends drive a `Chipate` and render
+//! its `Display` however they like.
+
+#[macro_use]
+extern crate log;
+
+extern crate rand;
+
+pub mod asm;
+pub mod audio;
+pub mod chipate;
+pub mod debugger;
+pub mod disasm;
+pub mod display;
+pub mod dump;
+pub mod error;
+pub mod frontend;
+pub mod keymap;
+pub mod movie;
+pub mod quirks;
+pub mod rewind;
+pub mod rng;
+pub mod scheduler;
+pub mod state;
+pub mod trace;
+pub mod watch;
+
+pub use chipate::Chipate;
+pub use debugger::Debugger;
+pub use display::Display;
+pub use error::EmuError;
+pub use frontend::{AudioSink, InputSource, VideoSink};
+pub use movie::Movie;
+pub use quirks::Quirks;
+pub use rewind::Rewind;
+pub use rng::Rng;
+pub use scheduler::Scheduler;
+pub use state::State;
@@ -0,0 +1,209 @@
+//! Movie Module
+//! Input recordings. A movie keeps the keypad state of every frame and the
+//! random bytes CXNN drew during it, together with what the machine was
+//! set up with, so the run can be replayed bit for bit without a player.
+
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+
+use chipate::Chipate;
+use error::EmuError;
+use frontend::InputSource;
+use quirks::Quirks;
+use scheduler::Scheduler;
+use state::{le16, le32, Reader};
+
+static MAGIC: &[u8] = b"CH8M";
+
+/// Bumped whenever the layout changes, older files are refused.
+pub const VERSION: u8 = 1;
+
+/// What one frame saw.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    pub keys: [u8; 16],
+    pub random: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Movie {
+    /// FNV-1a hash of memory from the load address up, taken after loading the ROM.
+    pub rom_hash: u64,
+    /// Instructions per second.
+    pub hz: u32,
+    pub quirks: Quirks,
+    pub load_address: u16,
+    pub xochip: bool,
+    pub rpl: [u8; 16],
+    pub frames: Vec<Frame>,
+}
+
+impl Movie {
+    /// Starts recording `chip`, which must be set up with its ROM loaded.
+    /// Also starts logging the random bytes CXNN draws.
+    pub fn record(chip: &mut Chipate, hz: u32) -> Movie {
+        chip.record_random(true);
+        Movie {
+            rom_hash: rom_hash(chip),
+            hz,
+            quirks: chip.quirks(),
+            load_address: chip.load_address(),
+            xochip: chip.xochip(),
+            rpl: chip.rpl_flags(),
+            frames: Vec::new(),
+        }
+    }
+
+    /// Adds the frame that just ran, call after every `Scheduler::run_frame`.
+    pub fn record_frame(&mut self, chip: &mut Chipate) {
+        self.frames.push(Frame {
+            keys: *chip.key(),
+            random: chip.take_random_log(),
+        });
+    }
+
+    /// Sets `chip` up the way the recording was, call before loading the ROM.
+    pub fn setup(&self, chip: &mut Chipate) {
+        chip.set_quirks(self.quirks);
+        chip.set_xochip(self.xochip);
+        chip.set_load_address(self.load_address);
+        chip.set_rpl_flags(self.rpl);
+    }
+
+    /// Fails unless `chip` has the ROM the movie was recorded with loaded.
+    pub fn check_rom(&self, chip: &Chipate) -> Result<(), EmuError> {
+        if rom_hash(chip) != self.rom_hash {
+            return Err(EmuError::InvalidMovie("it was recorded with a different ROM".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Presses the keys of frame `n` and queues its random bytes, false
+    /// once the movie is over.
+    pub fn apply_frame(&self, n: usize, chip: &mut Chipate) -> bool {
+        match self.frames.get(n) {
+            Some(f) => {
+                chip.set_keys(&mut Keys(f.keys));
+                chip.feed_random(&f.random);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Replays the whole movie on `chip` without a frontend. `chip` must be
+    /// set up with `setup` and have the ROM loaded.
+    pub fn play(&self, chip: &mut Chipate) -> Result<(), EmuError> {
+        self.check_rom(chip)?;
+        let mut scheduler = Scheduler::with_hz(self.hz);
+        for n in 0..self.frames.len() {
+            if chip.exited() {
+                break;
+            }
+            self.apply_frame(n, chip);
+            scheduler.run_frame(chip)?;
+        }
+        Ok(())
+    }
+
+    /// Binary layout, all numbers little endian:
+    ///
+    /// ```text
+    /// "CH8M" version flags quirks load_address hz rom_hash rpl[16]
+    /// frame count                   u32
+    /// every frame: keys, count, random bytes   u16 with bit N for key N, u32, bytes
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut b = Vec::new();
+        b.extend_from_slice(MAGIC);
+        b.push(VERSION);
+        b.push(self.xochip as u8);
+        b.push(self.quirks.to_bits());
+        b.extend_from_slice(&le16(self.load_address));
+        b.extend_from_slice(&le32(self.hz));
+        b.extend_from_slice(&le32(self.rom_hash as u32));
+        b.extend_from_slice(&le32((self.rom_hash >> 32) as u32));
+        b.extend_from_slice(&self.rpl);
+
+        b.extend_from_slice(&le32(self.frames.len() as u32));
+        for f in &self.frames {
+            let keys = f.keys.iter().enumerate().fold(0u16, |k, (n, &down)| k | ((down != 0) as u16) << n);
+            b.extend_from_slice(&le16(keys));
+            b.extend_from_slice(&le32(f.random.len() as u32));
+            b.extend_from_slice(&f.random);
+        }
+        b
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Movie, EmuError> {
+        let mut r = Reader::new(bytes, EmuError::InvalidMovie);
+        if r.take(4)? != MAGIC {
+            return Err(EmuError::InvalidMovie("not a movie".to_string()));
+        }
+        let version = r.u8()?;
+        if version != VERSION {
+            return Err(EmuError::InvalidMovie(format!("version {} is not supported, expected {}", version, VERSION)));
+        }
+
+        let xochip = r.u8()? != 0;
+        let quirks = Quirks::from_bits(r.u8()?);
+        let load_address = r.u16()?;
+        let hz = r.u32()?;
+        let rom_hash = r.u32()? as u64 | (r.u32()? as u64) << 32;
+        let rpl = r.array()?;
+
+        let count = r.u32()? as usize;
+        let mut frames = Vec::new();
+        for _ in 0..count {
+            let bits = r.u16()?;
+            let mut keys = [0; 16];
+            for (n, k) in keys.iter_mut().enumerate() {
+                *k = (bits >> n & 1) as u8;
+            }
+            let len = r.u32()? as usize;
+            let random = r.take(len)?.to_vec();
+            frames.push(Frame { keys, random });
+        }
+
+        Ok(Movie {
+            rom_hash,
+            hz,
+            quirks,
+            load_address,
+            xochip,
+            rpl,
+            frames,
+        })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), EmuError> {
+        let mut f = BufWriter::new(File::create(path)?);
+        f.write_all(&self.to_bytes())?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Movie, EmuError> {
+        let mut b = Vec::new();
+        File::open(path)?.read_to_end(&mut b)?;
+        Movie::from_bytes(&b)
+    }
+}
+
+/// Holds the recorded keys down for a frame.
+struct Keys([u8; 16]);
+
+impl InputSource for Keys {
+    fn poll(&mut self, key: &mut [u8; 16]) {
+        *key = self.0;
+    }
+}
+
+/// FNV-1a over memory from the load address up, which covers the ROM
+/// whatever its length.
+fn rom_hash(chip: &Chipate) -> u64 {
+    let from = (chip.load_address() as usize).min(chip.memory().len());
+    chip.memory()[from..]
+        .iter()
+        .fold(0xcbf29ce484222325, |h, &b| (h ^ b as u64).wrapping_mul(0x100000001b3))
+}
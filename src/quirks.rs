@@ -0,0 +1,128 @@
+//! Quirks Module
+//! Behaviour that differs between historical Chip-8 interpreters. ROMs are
+//! usually written against one of them, so pick the matching profile.
+
+/// Per opcode behaviour switches.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quirks {
+    /// 8XY6/8XYE copy VY into VX before shifting, instead of shifting VX in place.
+    pub shift_vy: bool,
+    /// FX55/FX65 leave I pointing past the last register touched.
+    pub increment_i: bool,
+    /// BNNN jumps to XNN plus VX instead of NNN plus V0.
+    pub jump_vx: bool,
+    /// 8XY1/8XY2/8XY3 reset VF to 0.
+    pub vf_reset: bool,
+    /// DXYN clips sprites at the screen edge instead of wrapping them around.
+    pub clip_sprites: bool,
+}
+
+/// Profile names accepted by `Quirks::from_profile`.
+pub static PROFILES: [&str; 4] = ["vip", "chip48", "schip", "xochip"];
+
+/// Flag names accepted by `Quirks::set`.
+pub static FLAGS: [&str; 5] = ["shift_vy", "increment_i", "jump_vx", "vf_reset", "clip_sprites"];
+
+impl Quirks {
+    /// The original COSMAC VIP interpreter.
+    pub fn cosmac_vip() -> Quirks {
+        Quirks {
+            shift_vy: true,
+            increment_i: true,
+            jump_vx: false,
+            vf_reset: true,
+            clip_sprites: true,
+        }
+    }
+
+    /// CHIP-48 on the HP-48 calculators.
+    pub fn chip48() -> Quirks {
+        Quirks {
+            shift_vy: false,
+            increment_i: true,
+            jump_vx: true,
+            vf_reset: false,
+            clip_sprites: true,
+        }
+    }
+
+    /// SUPER-CHIP 1.1.
+    pub fn superchip() -> Quirks {
+        Quirks {
+            shift_vy: false,
+            increment_i: false,
+            jump_vx: true,
+            vf_reset: false,
+            clip_sprites: true,
+        }
+    }
+
+    /// XO-CHIP as implemented by Octo.
+    pub fn xochip() -> Quirks {
+        Quirks {
+            shift_vy: true,
+            increment_i: true,
+            jump_vx: false,
+            vf_reset: false,
+            clip_sprites: false,
+        }
+    }
+
+    /// Looks up a profile by name, see `PROFILES`.
+    pub fn from_profile(name: &str) -> Option<Quirks> {
+        match name.to_lowercase().as_str() {
+            "vip" | "cosmac-vip" | "chip8" | "chip-8" => Some(Quirks::cosmac_vip()),
+            "chip48" | "chip-48" => Some(Quirks::chip48()),
+            "schip" | "superchip" | "super-chip" => Some(Quirks::superchip()),
+            "xochip" | "xo-chip" => Some(Quirks::xochip()),
+            _ => None,
+        }
+    }
+
+    /// Overrides a single flag by name, see `FLAGS`.
+    pub fn set(&mut self, name: &str, value: bool) -> Result<(), String> {
+        match name {
+            "shift_vy" => self.shift_vy = value,
+            "increment_i" => self.increment_i = value,
+            "jump_vx" => self.jump_vx = value,
+            "vf_reset" => self.vf_reset = value,
+            "clip_sprites" => self.clip_sprites = value,
+            _ => return Err(format!("Unknown quirk {}, expected one of {}", name, FLAGS.join(", "))),
+        }
+        Ok(())
+    }
+
+    /// Applies an override written as `name=on` or `name=off` (also true/false, 1/0).
+    pub fn apply_override(&mut self, spec: &str) -> Result<(), String> {
+        let mut parts = spec.splitn(2, '=');
+        let name = parts.next().unwrap_or("").trim();
+        let value = match parts.next().map(|v| v.trim().to_lowercase()) {
+            Some(ref v) if v == "on" || v == "true" || v == "1" => true,
+            Some(ref v) if v == "off" || v == "false" || v == "0" => false,
+            _ => return Err(format!("Invalid quirk override {}, expected name=on or name=off", spec)),
+        };
+        self.set(name, value)
+    }
+
+    /// One bit per flag in `FLAGS` order, for save states and movies.
+    pub fn to_bits(&self) -> u8 {
+        let flags = [self.shift_vy, self.increment_i, self.jump_vx, self.vf_reset, self.clip_sprites];
+        flags.iter().enumerate().fold(0, |b, (n, &on)| b | (on as u8) << n)
+    }
+
+    pub fn from_bits(b: u8) -> Quirks {
+        Quirks {
+            shift_vy: b & 1 != 0,
+            increment_i: b & 2 != 0,
+            jump_vx: b & 4 != 0,
+            vf_reset: b & 8 != 0,
+            clip_sprites: b & 16 != 0,
+        }
+    }
+}
+
+impl Default for Quirks {
+    fn default() -> Quirks {
+        Quirks::cosmac_vip()
+    }
+}
@@ -0,0 +1,71 @@
+//! Rewind Module
+//! Ring buffer of recent machine states, one pushed per frame, so a
+//! frontend can step back in time one frame at a time.
+
+use std::collections::VecDeque;
+
+use chipate::Chipate;
+use scheduler::FRAME_RATE;
+use state::State;
+
+pub struct Rewind {
+    states: VecDeque<State>,
+    capacity: usize,
+}
+
+impl Rewind {
+    /// Keeps the last `capacity` frames, 0 keeps none. The buffer grows as
+    /// frames are pushed rather than up front.
+    pub fn new(capacity: usize) -> Rewind {
+        Rewind {
+            states: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// Keeps the last `seconds` of 60 Hz frames. Each frame costs about
+    /// 12K, or 72K with the XO-CHIP memory.
+    pub fn with_seconds(seconds: u32) -> Rewind {
+        Rewind::new(seconds.saturating_mul(FRAME_RATE) as usize)
+    }
+
+    /// Remembers the current state, dropping the oldest one when full.
+    pub fn push(&mut self, chip: &Chipate) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.states.len() == self.capacity {
+            self.states.pop_front();
+        }
+        self.states.push_back(chip.save_state());
+    }
+
+    /// Puts `chip` back one frame, false once there is nothing left.
+    pub fn step_back(&mut self, chip: &mut Chipate) -> bool {
+        match self.states.pop_back() {
+            Some(s) => {
+                chip.load_state(&s);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The most frames kept.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Frames that can still be stepped back.
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.states.clear();
+    }
+}
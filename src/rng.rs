@@ -0,0 +1,60 @@
+//! Rng Module
+//! Where CXNN gets its random bytes. `Chipate::set_rng` swaps the source,
+//! a `SeededRng` with a known seed makes a run repeatable and a
+//! `SequenceRng` hands out fixed bytes for tests.
+
+use rand;
+
+pub trait Rng {
+    fn next_u8(&mut self) -> u8;
+}
+
+/// SplitMix64, the same seed always gives the same bytes.
+pub struct SeededRng {
+    state: u64,
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> SeededRng {
+        SeededRng { state: seed }
+    }
+}
+
+impl Rng for SeededRng {
+    fn next_u8(&mut self) -> u8 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        ((z ^ (z >> 31)) >> 56) as u8
+    }
+}
+
+/// Hands out the given bytes in order, starting over after the last one.
+/// An empty sequence always gives 0.
+pub struct SequenceRng {
+    bytes: Vec<u8>,
+    at: usize,
+}
+
+impl SequenceRng {
+    pub fn new(bytes: &[u8]) -> SequenceRng {
+        SequenceRng { bytes: bytes.to_vec(), at: 0 }
+    }
+}
+
+impl Rng for SequenceRng {
+    fn next_u8(&mut self) -> u8 {
+        if self.bytes.is_empty() {
+            return 0;
+        }
+        let b = self.bytes[self.at];
+        self.at = (self.at + 1) % self.bytes.len();
+        b
+    }
+}
+
+/// A fresh seed for when none was asked for, log it to be able to repeat the run.
+pub fn random_seed() -> u64 {
+    rand::random()
+}
@@ -0,0 +1,112 @@
+//! Scheduler Module
+//! Runs a `Chipate` one 60 Hz frame at a time: a batch of instructions,
+//! one tick of the delay and sound timers, then a wait for the next frame.
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chipate::Chipate;
+use error::EmuError;
+
+/// Frames per second, which is also the rate the timers count down at.
+pub const FRAME_RATE: u32 = 60;
+
+pub struct Scheduler {
+    // Instructions per second
+    hz: u32,
+
+    // Leftover instructions, in 1/60ths, when hz is not a multiple of 60
+    budget: u32,
+
+    // Instructions still to run in a frame a stop interrupted
+    rest: Option<u32>,
+
+    // Frame deadlines are measured from here so rounding never adds up
+    start: Instant,
+    frames: u64,
+}
+
+impl Scheduler {
+    /// Runs `hz` instructions per second.
+    pub fn with_hz(hz: u32) -> Scheduler {
+        Scheduler {
+            hz,
+            budget: 0,
+            rest: None,
+            start: Instant::now(),
+            frames: 0,
+        }
+    }
+
+    /// Runs `ipf` instructions per frame.
+    pub fn with_ipf(ipf: u32) -> Scheduler {
+        Scheduler::with_hz(ipf.saturating_mul(FRAME_RATE))
+    }
+
+    pub fn hz(&self) -> u32 {
+        self.hz
+    }
+
+    /// Runs one frame worth of instructions, then ticks the timers.
+    pub fn run_frame(&mut self, chip: &mut Chipate) -> Result<(), EmuError> {
+        self.run_frame_until(chip, |_| false).map(|_| ())
+    }
+
+    /// Same as `run_frame`, asking `stop` before every instruction whether to
+    /// halt. Returns true when it did, the next call then carries on with the
+    /// rest of the frame and the timers tick once it completes.
+    pub fn run_frame_until<F>(&mut self, chip: &mut Chipate, mut stop: F) -> Result<bool, EmuError>
+        where F: FnMut(&Chipate) -> bool
+    {
+        let n = match self.rest.take() {
+            Some(n) => n,
+            None => {
+                self.budget = self.budget.saturating_add(self.hz);
+                let n = self.budget / FRAME_RATE;
+                self.budget %= FRAME_RATE;
+                n
+            }
+        };
+
+        for done in 0..n {
+            if chip.exited() {
+                break;
+            }
+            if stop(chip) {
+                self.rest = Some(n - done);
+                return Ok(true);
+            }
+            chip.emulate_cycle()?;
+        }
+
+        chip.tick_timers();
+        Ok(false)
+    }
+
+    /// Restarts the frame clock from now, so time spent away from the
+    /// emulator, e.g. at a debugger prompt, is not raced through afterwards.
+    pub fn restart_clock(&mut self) {
+        self.start = Instant::now();
+        self.frames = 0;
+    }
+
+    /// Sleeps until the next frame is due. When running more than a few
+    /// frames late the schedule restarts from now instead of rushing to catch up.
+    pub fn wait(&mut self) {
+        self.frames += 1;
+        let due = self.start + frame_time(self.frames);
+        let now = Instant::now();
+
+        if now < due {
+            thread::sleep(due - now);
+        } else if now - due > frame_time(5) {
+            debug!("Running {:?} behind, resetting the frame clock", now - due);
+            self.restart_clock();
+        }
+    }
+}
+
+/// Time taken by `frames` frames.
+fn frame_time(frames: u64) -> Duration {
+    Duration::from_nanos(frames * 1_000_000_000 / FRAME_RATE as u64)
+}
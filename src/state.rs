@@ -0,0 +1,239 @@
+//! State Module
+//! Snapshots of the whole machine. `Chipate::save_state` takes one and
+//! `Chipate::load_state` puts it back, `to_bytes`/`from_bytes` turn it into
+//! a versioned binary file for save slots. The random source CXNN draws
+//! from is not saved, after loading it carries on from wherever it is, so
+//! only a movie repeats random numbers exactly.
+
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+
+use display::{Display, HEIGHT, WIDTH};
+use error::EmuError;
+use quirks::Quirks;
+
+static MAGIC: &[u8] = b"CH8S";
+
+/// Bumped whenever the layout below changes, older files are refused.
+pub const VERSION: u8 = 2;
+
+/// Everything needed to resume a program exactly where it was.
+#[derive(Clone)]
+pub struct State {
+    pub opcode: u16,
+    pub memory: Vec<u8>,
+    pub v: [u8; 16],
+    pub i: u16,
+    pub pc: u16,
+    pub stack: Vec<u16>,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub key: [u8; 16],
+    pub key_wait: Option<u8>,
+    pub display: Display,
+    pub load_address: u16,
+    pub quirks: Quirks,
+    pub rpl: [u8; 16],
+    pub exited: bool,
+    pub xochip: bool,
+    pub pattern: [u8; 16],
+    pub pitch: u8,
+}
+
+impl State {
+    /// Binary layout, all numbers little endian:
+    ///
+    /// ```text
+    /// "CH8S" version flags quirks
+    /// load_address pc i opcode      u16 each
+    /// v[16] delay_timer sound_timer
+    /// stack length, stack           u8, u16 each
+    /// key[16] rpl[16] pattern[16] pitch planes
+    /// memory length, memory         u32, bytes
+    /// framebuffer                   128x64 bytes
+    /// key FX0A waits to be released u8, 0xFF for none
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut b = Vec::with_capacity(self.memory.len() + WIDTH * HEIGHT + 128);
+        b.extend_from_slice(MAGIC);
+        b.push(VERSION);
+        b.push(flag(self.xochip, 0) | flag(self.exited, 1) | flag(self.display.hires(), 2));
+        b.push(self.quirks.to_bits());
+
+        for &w in &[self.load_address, self.pc, self.i, self.opcode] {
+            b.extend_from_slice(&le16(w));
+        }
+        b.extend_from_slice(&self.v);
+        b.push(self.delay_timer);
+        b.push(self.sound_timer);
+
+        b.push(self.stack.len() as u8);
+        for &addr in &self.stack {
+            b.extend_from_slice(&le16(addr));
+        }
+
+        b.extend_from_slice(&self.key);
+        b.extend_from_slice(&self.rpl);
+        b.extend_from_slice(&self.pattern);
+        b.push(self.pitch);
+        b.push(self.display.planes());
+
+        b.extend_from_slice(&le32(self.memory.len() as u32));
+        b.extend_from_slice(&self.memory);
+
+        for row in self.display.gfx().iter() {
+            b.extend_from_slice(row);
+        }
+        b.push(self.key_wait.unwrap_or(0xFF));
+        b
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<State, EmuError> {
+        let mut r = Reader::new(bytes, EmuError::InvalidState);
+        if r.take(4)? != MAGIC {
+            return Err(EmuError::InvalidState("not a save state".to_string()));
+        }
+        let version = r.u8()?;
+        if version != VERSION {
+            return Err(EmuError::InvalidState(format!("version {} is not supported, expected {}", version, VERSION)));
+        }
+
+        let flags = r.u8()?;
+        let quirks = Quirks::from_bits(r.u8()?);
+        let load_address = r.u16()?;
+        let pc = r.u16()?;
+        let i = r.u16()?;
+        let opcode = r.u16()?;
+        let v = r.array()?;
+        let delay_timer = r.u8()?;
+        let sound_timer = r.u8()?;
+
+        let depth = r.u8()? as usize;
+        if depth > 16 {
+            return Err(EmuError::InvalidState(format!("stack depth {} is more than 16", depth)));
+        }
+        let mut stack = Vec::with_capacity(depth);
+        for _ in 0..depth {
+            stack.push(r.u16()?);
+        }
+
+        let key = r.array()?;
+        let rpl = r.array()?;
+        let pattern = r.array()?;
+        let pitch = r.u8()?;
+        let planes = r.u8()?;
+
+        let len = r.u32()? as usize;
+        if len != 0x1000 && len != 0x10000 {
+            return Err(EmuError::InvalidState(format!("memory size {} is neither 4K nor 64K", len)));
+        }
+        let xochip = flags & 1 != 0;
+        if xochip != (len == 0x10000) {
+            return Err(EmuError::InvalidState(format!("memory size {} does not match XO-CHIP {}",
+                                                      len,
+                                                      if xochip { "on" } else { "off" })));
+        }
+        let memory = r.take(len)?.to_vec();
+
+        let mut gfx = [[0; WIDTH]; HEIGHT];
+        for row in gfx.iter_mut() {
+            row.copy_from_slice(r.take(WIDTH)?);
+        }
+        let mut display = Display::new();
+        display.restore(gfx, flags & 4 != 0, planes);
+
+        let key_wait = match r.u8()? {
+            0xFF => None,
+            k if k < 16 => Some(k),
+            k => return Err(EmuError::InvalidState(format!("key {} is not on the keypad", k))),
+        };
+
+        Ok(State {
+            opcode,
+            memory,
+            v,
+            i,
+            pc,
+            stack,
+            delay_timer,
+            sound_timer,
+            key,
+            key_wait,
+            display,
+            load_address,
+            quirks,
+            rpl,
+            exited: flags & 2 != 0,
+            xochip,
+            pattern,
+            pitch,
+        })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), EmuError> {
+        let mut f = BufWriter::new(File::create(path)?);
+        f.write_all(&self.to_bytes())?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<State, EmuError> {
+        let mut b = Vec::new();
+        File::open(path)?.read_to_end(&mut b)?;
+        State::from_bytes(&b)
+    }
+}
+
+/// Reads through a save state or movie, failing cleanly when it is cut short.
+pub(crate) struct Reader<'b> {
+    bytes: &'b [u8],
+    at: usize,
+    invalid: fn(String) -> EmuError,
+}
+
+impl<'b> Reader<'b> {
+    pub(crate) fn new(bytes: &'b [u8], invalid: fn(String) -> EmuError) -> Reader<'b> {
+        Reader { bytes, at: 0, invalid }
+    }
+
+    pub(crate) fn take(&mut self, n: usize) -> Result<&'b [u8], EmuError> {
+        if self.bytes.len() - self.at < n {
+            return Err((self.invalid)("file is truncated".to_string()));
+        }
+        let s = &self.bytes[self.at..self.at + n];
+        self.at += n;
+        Ok(s)
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, EmuError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> Result<u16, EmuError> {
+        let b = self.take(2)?;
+        Ok(b[0] as u16 | (b[1] as u16) << 8)
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, EmuError> {
+        let b = self.take(4)?;
+        Ok(b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24)
+    }
+
+    pub(crate) fn array(&mut self) -> Result<[u8; 16], EmuError> {
+        let mut a = [0; 16];
+        a.copy_from_slice(self.take(16)?);
+        Ok(a)
+    }
+}
+
+fn flag(on: bool, bit: u8) -> u8 {
+    (on as u8) << bit
+}
+
+pub(crate) fn le16(v: u16) -> [u8; 2] {
+    [v as u8, (v >> 8) as u8]
+}
+
+pub(crate) fn le32(v: u32) -> [u8; 4] {
+    [v as u8, (v >> 8) as u8, (v >> 16) as u8, (v >> 24) as u8]
+}
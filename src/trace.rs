@@ -0,0 +1,381 @@
+//! Trace Module
+//! One record per executed instruction: cycle number, PC, opcode, its
+//! disassembly and what it changed in the V registers, I and the stack.
+//! The frontend calls `Tracer::before` ahead of every instruction, from
+//! `Scheduler::run_frame_until`, and the record for an instruction is
+//! written once the next one comes up.
+//!
+//! Text records are tab separated, numbers in hex without a prefix:
+//!
+//! ```text
+//! cycle  pc   opcode  disassembly   changes
+//! 3      0204 8014    v0 += v1      V0=00 VF=01
+//! 4      0206 2300    :call 0x300   stack=0208
+//! ```
+//!
+//! JSON records hold the same fields, numbers in decimal:
+//!
+//! ```text
+//! {"cycle":3,"pc":516,"opcode":32788,"asm":"v0 += v1","changes":{"V0":0,"VF":1}}
+//! ```
+//!
+//! `Differ` goes the other way and checks the machine against a trace taken
+//! from another emulator, one line per instruction holding the state ahead
+//! of it. Fields are `NAME=VALUE` or `NAME:VALUE` in hex, for PC, OP, I and
+//! V0 to VF, anything else on the line is ignored:
+//!
+//! ```text
+//! PC:0204 OP:8014 I:0000 V0:05 V1:FB
+//! ```
+
+use std::fmt;
+use std::io::{self, Write};
+
+use chipate::Chipate;
+use disasm;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "text" => Some(Format::Text),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Machine state ahead of an instruction.
+struct Before {
+    pc: u16,
+    opcode: u16,
+    long: Option<u16>,
+    v: [u8; 16],
+    i: u16,
+    stack: Vec<u16>,
+}
+
+impl Before {
+    fn take(chip: &Chipate) -> Option<Before> {
+        let at = chip.pc() as usize;
+        Some(Before {
+            pc: chip.pc(),
+            opcode: word(chip, at)?,
+            long: word(chip, at + 2),
+            v: *chip.v(),
+            i: chip.i(),
+            stack: chip.stack().to_vec(),
+        })
+    }
+}
+
+/// The big endian word at `addr`, None past the end of memory.
+fn word(chip: &Chipate, addr: usize) -> Option<u16> {
+    chip.memory().get(addr..addr + 2).map(|b| (b[0] as u16) << 8 | b[1] as u16)
+}
+
+pub struct Tracer<W: Write> {
+    out: W,
+    format: Format,
+    cycle: u64,
+    pending: Option<Before>,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> Tracer<W> {
+    pub fn new(out: W, format: Format) -> Tracer<W> {
+        Tracer {
+            out,
+            format,
+            cycle: 0,
+            pending: None,
+            error: None,
+        }
+    }
+
+    /// Call before every instruction. Writes the record of the previous one.
+    /// Always returns false so it can stand in for a `run_frame_until` stop.
+    pub fn before(&mut self, chip: &Chipate) -> bool {
+        self.finish(chip);
+        self.pending = Before::take(chip);
+        false
+    }
+
+    /// Writes the record of the last instruction and flushes, call when done
+    /// and before changing the machine behind the tracer's back, e.g. loading
+    /// a save state. Returns the first write error hit so far.
+    pub fn flush(&mut self, chip: &Chipate) -> io::Result<()> {
+        self.finish(chip);
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        self.out.flush()
+    }
+
+    fn finish(&mut self, chip: &Chipate) {
+        let before = match self.pending.take() {
+            Some(b) => b,
+            None => return,
+        };
+        let record = match self.format {
+            Format::Text => text(self.cycle, &before, chip),
+            Format::Json => json(self.cycle, &before, chip),
+        };
+        self.cycle += 1;
+
+        if self.error.is_none() {
+            if let Err(e) = self.out.write_all(record.as_bytes()) {
+                self.error = Some(e);
+            }
+        }
+    }
+}
+
+fn asm(b: &Before) -> String {
+    disasm::disassemble(b.opcode, b.long).unwrap_or_else(|| format!("0x{:04X}", b.opcode))
+}
+
+/// The V registers that changed, as (index, new value).
+fn changed_v<'a>(b: &'a Before, chip: &'a Chipate) -> impl Iterator<Item = (usize, u8)> + 'a {
+    b.v.iter().zip(chip.v().iter()).enumerate().filter(|&(_, (old, new))| old != new).map(|(x, (_, &new))| (x, new))
+}
+
+fn text(cycle: u64, b: &Before, chip: &Chipate) -> String {
+    let mut changes = Vec::new();
+    for (x, value) in changed_v(b, chip) {
+        changes.push(format!("V{:X}={:02X}", x, value));
+    }
+    if chip.i() != b.i {
+        changes.push(format!("I={:04X}", chip.i()));
+    }
+    if chip.stack() != &b.stack[..] {
+        let stack: Vec<String> = chip.stack().iter().map(|a| format!("{:04X}", a)).collect();
+        changes.push(format!("stack={}", if stack.is_empty() { "-".to_string() } else { stack.join(",") }));
+    }
+    format!("{}\t{:04X}\t{:04X}\t{}\t{}\n", cycle, b.pc, b.opcode, asm(b), changes.join(" "))
+}
+
+fn json(cycle: u64, b: &Before, chip: &Chipate) -> String {
+    let mut changes = Vec::new();
+    for (x, value) in changed_v(b, chip) {
+        changes.push(format!("\"V{:X}\":{}", x, value));
+    }
+    if chip.i() != b.i {
+        changes.push(format!("\"I\":{}", chip.i()));
+    }
+    if chip.stack() != &b.stack[..] {
+        let stack: Vec<String> = chip.stack().iter().map(|a| a.to_string()).collect();
+        changes.push(format!("\"stack\":[{}]", stack.join(",")));
+    }
+
+    let mut asm_json = String::new();
+    for c in asm(b).chars() {
+        if c == '"' || c == '\\' {
+            asm_json.push('\\');
+        }
+        asm_json.push(c);
+    }
+    format!("{{\"cycle\":{},\"pc\":{},\"opcode\":{},\"asm\":\"{}\",\"changes\":{{{}}}}}\n",
+            cycle,
+            b.pc,
+            b.opcode,
+            asm_json,
+            changes.join(","))
+}
+
+/// One line of a reference trace: the state ahead of an instruction. Fields
+/// the line leaves out are not checked.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Step {
+    /// Line number in the trace, from 1.
+    pub line: usize,
+    pub pc: Option<u16>,
+    pub opcode: Option<u16>,
+    pub v: [Option<u8>; 16],
+    pub i: Option<u16>,
+}
+
+impl Step {
+    /// Parses one line, None when it holds none of the fields, e.g. a header.
+    pub fn parse(line: usize, text: &str) -> Result<Option<Step>, String> {
+        let mut step = Step { line, ..Step::default() };
+        let mut found = false;
+        let mut tokens = text.split(|c: char| c.is_whitespace() || c == ',').filter(|t| !t.is_empty());
+        while let Some(token) = tokens.next() {
+            let (name, value) = match token.find(['=', ':']) {
+                Some(n) => (&token[..n], &token[n + 1..]),
+                None => continue,
+            };
+            let name = name.to_uppercase();
+            if !is_field(&name) {
+                continue;
+            }
+            // "PC: 0200" puts the value in the next token
+            let value = if value.is_empty() { tokens.next().unwrap_or("") } else { value };
+            let digits = value.trim_start_matches("0x").trim_start_matches("0X");
+            let invalid = || format!("line {}: invalid {} value {}", line, name, value);
+            let number = u16::from_str_radix(digits, 16).map_err(|_| invalid())?;
+            match name.as_str() {
+                "PC" => step.pc = Some(number),
+                "OP" | "OPCODE" => step.opcode = Some(number),
+                "I" => step.i = Some(number),
+                _ => {
+                    let x = usize::from_str_radix(&name[1..], 16).unwrap();
+                    if number > 0xFF {
+                        return Err(invalid());
+                    }
+                    step.v[x] = Some(number as u8);
+                }
+            }
+            found = true;
+        }
+        Ok(if found { Some(step) } else { None })
+    }
+
+    /// What the machine has different from this step.
+    pub fn mismatches(&self, chip: &Chipate) -> Vec<Mismatch> {
+        let opcode = word(chip, chip.pc() as usize).unwrap_or(0);
+        let mut fields = vec![("PC".to_string(), self.pc, chip.pc()),
+                              ("OP".to_string(), self.opcode, opcode),
+                              ("I".to_string(), self.i, chip.i())];
+        for (x, &want) in self.v.iter().enumerate() {
+            fields.push((format!("V{:X}", x), want.map(u16::from), chip.v()[x] as u16));
+        }
+        fields.into_iter()
+            .filter_map(|(field, want, got)| match want {
+                Some(expected) if expected != got => Some(Mismatch { field, expected, got }),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+fn is_field(name: &str) -> bool {
+    match name {
+        "PC" | "OP" | "OPCODE" | "I" => true,
+        _ => name.len() == 2 && name.starts_with('V') && name[1..].chars().all(|c| c.is_ascii_hexdigit()),
+    }
+}
+
+/// Parses a whole reference trace.
+pub fn parse_reference(text: &str) -> Result<Vec<Step>, String> {
+    let mut steps = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        if let Some(step) = Step::parse(n + 1, line)? {
+            steps.push(step);
+        }
+    }
+    Ok(steps)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mismatch {
+    pub field: String,
+    pub expected: u16,
+    pub got: u16,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width = if self.field.starts_with('V') { 2 } else { 4 };
+        write!(f, "{}: expected {:03$X}, got {:03$X}", self.field, self.expected, self.got, width)
+    }
+}
+
+/// Where the machine stopped following the reference.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    /// Index of the reference step that did not match, from 0 like the
+    /// cycle numbers of a `Tracer`.
+    pub step: usize,
+    pub line: usize,
+    /// The instruction run just before, as `PPPP OOOO disassembly`.
+    pub after: Option<String>,
+    /// Empty when the program exited before reaching the step.
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.mismatches.is_empty() {
+            write!(f, "Program exited before step {} (line {})", self.step, self.line)?;
+        } else {
+            write!(f, "Step {} (line {}) differs", self.step, self.line)?;
+        }
+        if let Some(ref after) = self.after {
+            write!(f, " after {}", after)?;
+        }
+        for m in &self.mismatches {
+            write!(f, "\n  {}", m)?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks the machine against a reference trace ahead of every instruction.
+pub struct Differ<'a> {
+    steps: &'a [Step],
+    next: usize,
+    last: Option<Before>,
+    divergence: Option<Divergence>,
+}
+
+impl<'a> Differ<'a> {
+    pub fn new(steps: &'a [Step]) -> Differ<'a> {
+        Differ {
+            steps,
+            next: 0,
+            last: None,
+            divergence: None,
+        }
+    }
+
+    /// Call before every instruction. Returns true to stop, once the machine
+    /// diverged or the reference ran out, for use as a `run_frame_until` stop.
+    pub fn before(&mut self, chip: &Chipate) -> bool {
+        let step = match self.steps.get(self.next) {
+            Some(s) => s,
+            None => return true,
+        };
+        let mismatches = step.mismatches(chip);
+        if !mismatches.is_empty() {
+            self.diverge(mismatches);
+            return true;
+        }
+        self.next += 1;
+        self.last = Before::take(chip);
+        false
+    }
+
+    /// Whether the whole reference has been checked, or the machine diverged.
+    pub fn done(&self) -> bool {
+        self.divergence.is_some() || self.next == self.steps.len()
+    }
+
+    /// The number of steps that matched, or where the machine diverged. A
+    /// program exiting before the end of the reference counts as diverging.
+    pub fn finish(mut self) -> Result<usize, Divergence> {
+        if !self.done() {
+            self.diverge(Vec::new());
+        }
+        match self.divergence {
+            Some(d) => Err(d),
+            None => Ok(self.next),
+        }
+    }
+
+    fn diverge(&mut self, mismatches: Vec<Mismatch>) {
+        let after = self.last.as_ref().map(|b| format!("{:04X} {:04X} {}", b.pc, b.opcode, asm(b)));
+        self.divergence = Some(Divergence {
+            step: self.next,
+            line: self.steps[self.next].line,
+            after,
+            mismatches,
+        });
+    }
+}
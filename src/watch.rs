@@ -0,0 +1,346 @@
+//! Watch Module
+//! Watchpoints on memory, the V registers, I and the timers. `accesses`
+//! works out what the instruction at the PC is about to read and write, the
+//! debugger checks that against its watchpoints once the instruction ran.
+//! `countdown` turns the 60 Hz timer ticks in between into writes as well.
+
+use std::fmt;
+
+use chipate::Chipate;
+
+/// Something a watchpoint can look at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+    Memory(u16),
+    V(usize),
+    I,
+    DelayTimer,
+    SoundTimer,
+}
+
+impl Target {
+    /// Parses V0 to VF, I, DT, ST or a hex memory address.
+    pub fn parse(s: &str) -> Result<Target, String> {
+        let upper = s.to_uppercase();
+        match upper.as_str() {
+            "I" => return Ok(Target::I),
+            "DT" => return Ok(Target::DelayTimer),
+            "ST" => return Ok(Target::SoundTimer),
+            _ => {}
+        }
+
+        if upper.len() == 2 && upper.starts_with('V') {
+            if let Ok(x) = usize::from_str_radix(&upper[1..], 16) {
+                return Ok(Target::V(x));
+            }
+        }
+
+        let digits = upper.trim_start_matches("0X");
+        u16::from_str_radix(digits, 16)
+            .map(Target::Memory)
+            .map_err(|_| format!("Unknown watch target {}, expected VX, I, DT, ST or an address", s))
+    }
+
+    /// Current value, None for memory past the end.
+    pub fn value(&self, chip: &Chipate) -> Option<u16> {
+        match *self {
+            Target::Memory(addr) => chip.memory().get(addr as usize).map(|&b| b as u16),
+            Target::V(x) => Some(chip.v()[x] as u16),
+            Target::I => Some(chip.i()),
+            Target::DelayTimer => Some(chip.delay_timer() as u16),
+            Target::SoundTimer => Some(chip.sound_timer() as u16),
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Target::Memory(addr) => write!(f, "0x{:03X}", addr),
+            Target::V(x) => write!(f, "V{:X}", x),
+            Target::I => write!(f, "I"),
+            Target::DelayTimer => write!(f, "DT"),
+            Target::SoundTimer => write!(f, "ST"),
+        }
+    }
+}
+
+/// One read or write done by an instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Access {
+    pub target: Target,
+    pub write: bool,
+}
+
+/// Comparison used by a watchpoint condition.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compare {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Compare {
+    pub fn parse(s: &str) -> Option<Compare> {
+        match s {
+            "==" => Some(Compare::Eq),
+            "!=" => Some(Compare::Ne),
+            "<" => Some(Compare::Lt),
+            "<=" => Some(Compare::Le),
+            ">" => Some(Compare::Gt),
+            ">=" => Some(Compare::Ge),
+            _ => None,
+        }
+    }
+
+    pub fn holds(&self, a: u16, b: u16) -> bool {
+        match *self {
+            Compare::Eq => a == b,
+            Compare::Ne => a != b,
+            Compare::Lt => a < b,
+            Compare::Le => a <= b,
+            Compare::Gt => a > b,
+            Compare::Ge => a >= b,
+        }
+    }
+
+    fn symbol(&self) -> &'static str {
+        match *self {
+            Compare::Eq => "==",
+            Compare::Ne => "!=",
+            Compare::Lt => "<",
+            Compare::Le => "<=",
+            Compare::Gt => ">",
+            Compare::Ge => ">=",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub target: Target,
+    pub on_read: bool,
+    pub on_write: bool,
+
+    // Only trigger while the value, after the instruction, compares true
+    pub condition: Option<(Compare, u16)>,
+}
+
+impl Watchpoint {
+    /// Triggers on writes, like a plain `watch` in gdb.
+    pub fn write(target: Target) -> Watchpoint {
+        Watchpoint {
+            target,
+            on_read: false,
+            on_write: true,
+            condition: None,
+        }
+    }
+
+    pub fn read(target: Target) -> Watchpoint {
+        Watchpoint {
+            on_read: true,
+            on_write: false,
+            ..Watchpoint::write(target)
+        }
+    }
+
+    pub fn access(target: Target) -> Watchpoint {
+        Watchpoint {
+            on_read: true,
+            ..Watchpoint::write(target)
+        }
+    }
+
+    pub fn when(self, op: Compare, value: u16) -> Watchpoint {
+        Watchpoint { condition: Some((op, value)), ..self }
+    }
+
+    /// Whether `access` sets this off, `chip` being the state after it happened.
+    pub fn triggered_by(&self, access: &Access, chip: &Chipate) -> bool {
+        if access.target != self.target {
+            return false;
+        }
+        if !(access.write && self.on_write || !access.write && self.on_read) {
+            return false;
+        }
+
+        match self.condition {
+            Some((op, value)) => self.target.value(chip).is_some_and(|v| op.holds(v, value)),
+            None => true,
+        }
+    }
+}
+
+impl fmt::Display for Watchpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match (self.on_read, self.on_write) {
+            (true, true) => "access",
+            (true, false) => "read",
+            _ => "write",
+        };
+        write!(f, "{} {}", kind, self.target)?;
+        if let Some((op, value)) = self.condition {
+            write!(f, " {} 0x{:X}", op.symbol(), value)?;
+        }
+        Ok(())
+    }
+}
+
+/// The timers that counted down on their own since they were `timers`,
+/// delay then sound, as writes. Writes by an instruction are not told apart,
+/// leave out the timers it wrote.
+pub fn countdown(timers: (u8, u8), chip: &Chipate) -> Vec<Access> {
+    let mut a = Accesses(Vec::new());
+    if chip.delay_timer() < timers.0 {
+        a.write(Target::DelayTimer);
+    }
+    if chip.sound_timer() < timers.1 {
+        a.write(Target::SoundTimer);
+    }
+    a.0
+}
+
+/// What the instruction at the PC reads and writes, given the current
+/// registers and quirks. Instruction fetches, the stack and the timers
+/// counting down on their own are left out.
+pub fn accesses(chip: &Chipate) -> Vec<Access> {
+    let pc = chip.pc() as usize;
+    let opcode = match (chip.memory().get(pc), chip.memory().get(pc + 1)) {
+        (Some(&a), Some(&b)) => (a as u16) << 8 | b as u16,
+        _ => return Vec::new(),
+    };
+
+    let x = ((opcode & 0x0F00) >> 8) as usize;
+    let y = ((opcode & 0x00F0) >> 4) as usize;
+    let n = (opcode & 0x000F) as usize;
+    let quirks = chip.quirks();
+    let i = chip.i();
+
+    let mut a = Accesses(Vec::new());
+    match opcode & 0xF000 {
+        0x3000 | 0x4000 | 0xE000 => a.read(Target::V(x)),
+        0x5000 | 0x9000 if n == 0 => {
+            a.read(Target::V(x));
+            a.read(Target::V(y));
+        }
+        0x5000 if n == 2 || n == 3 => {
+            let store = n == 2;
+            a.read(Target::I);
+            for r in x.min(y)..x.max(y) + 1 {
+                if store { a.read(Target::V(r)) } else { a.write(Target::V(r)) }
+            }
+            a.memory(i, x.max(y) - x.min(y) + 1, store);
+        }
+        0x6000 | 0xC000 => a.write(Target::V(x)),
+        0x7000 => {
+            a.read(Target::V(x));
+            a.write(Target::V(x));
+        }
+        0x8000 => {
+            match n {
+                0x0 => a.read(Target::V(y)),
+                0x6 | 0xE if quirks.shift_vy => a.read(Target::V(y)),
+                0x6 | 0xE => a.read(Target::V(x)),
+                _ => {
+                    a.read(Target::V(x));
+                    a.read(Target::V(y));
+                }
+            }
+            a.write(Target::V(x));
+            match n {
+                0x1..=0x3 if quirks.vf_reset => a.write(Target::V(0xF)),
+                0x4..=0x7 | 0xE => a.write(Target::V(0xF)),
+                _ => {}
+            }
+        }
+        0xA000 => a.write(Target::I),
+        0xB000 => a.read(Target::V(if quirks.jump_vx { x } else { 0 })),
+        0xD000 => {
+            a.read(Target::V(x));
+            a.read(Target::V(y));
+            a.read(Target::I);
+            let len = if n == 0 { 32 } else { n } * chip.display.plane_count();
+            a.memory(i, len, false);
+            a.write(Target::V(0xF));
+        }
+        0xF000 => {
+            match opcode {
+                0xF000 => a.write(Target::I),
+                0xF002 => {
+                    a.read(Target::I);
+                    a.memory(i, 16, false);
+                }
+                _ => {}
+            }
+            match opcode & 0x00FF {
+                0x07 => {
+                    a.read(Target::DelayTimer);
+                    a.write(Target::V(x));
+                }
+                0x0A if chip.key().iter().any(|&k| k != 0) => a.write(Target::V(x)),
+                0x15 => {
+                    a.read(Target::V(x));
+                    a.write(Target::DelayTimer);
+                }
+                0x18 => {
+                    a.read(Target::V(x));
+                    a.write(Target::SoundTimer);
+                }
+                0x1E => {
+                    a.read(Target::V(x));
+                    a.read(Target::I);
+                    a.write(Target::I);
+                }
+                0x29 | 0x30 => {
+                    a.read(Target::V(x));
+                    a.write(Target::I);
+                }
+                0x33 => {
+                    a.read(Target::V(x));
+                    a.read(Target::I);
+                    a.memory(i, 3, true);
+                }
+                0x3A => a.read(Target::V(x)),
+                0x55 | 0x65 => {
+                    let store = opcode & 0x00FF == 0x55;
+                    a.read(Target::I);
+                    for r in 0..x + 1 {
+                        if store { a.read(Target::V(r)) } else { a.write(Target::V(r)) }
+                    }
+                    a.memory(i, x + 1, store);
+                    if quirks.increment_i {
+                        a.write(Target::I);
+                    }
+                }
+                0x75 => (0..x + 1).for_each(|r| a.read(Target::V(r))),
+                0x85 => (0..x + 1).for_each(|r| a.write(Target::V(r))),
+                _ => {}
+            }
+        }
+        _ => {}
+    }
+    a.0
+}
+
+struct Accesses(Vec<Access>);
+
+impl Accesses {
+    fn read(&mut self, target: Target) {
+        self.0.push(Access { target, write: false });
+    }
+
+    fn write(&mut self, target: Target) {
+        self.0.push(Access { target, write: true });
+    }
+
+    fn memory(&mut self, from: u16, len: usize, write: bool) {
+        for n in 0..len {
+            let target = Target::Memory(from.wrapping_add(n as u16));
+            self.0.push(Access { target, write });
+        }
+    }
+}
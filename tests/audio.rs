@@ -0,0 +1,70 @@
+//! The beep rendered offline through `WavSink`.
+
+extern crate chipate;
+
+mod common;
+
+use chipate::audio::{ToneGenerator, WavSink};
+use chipate::Scheduler;
+
+static SAMPLE_RATE: u32 = 44100;
+
+fn le16(b: &[u8], at: usize) -> u16 {
+    b[at] as u16 | (b[at + 1] as u16) << 8
+}
+
+fn le32(b: &[u8], at: usize) -> u32 {
+    le16(b, at) as u32 | (le16(b, at + 2) as u32) << 16
+}
+
+/// Runs `frames` frames of a program that sets the sound timer to 5 and
+/// spins, returning the sink and the sound timer each frame was played with.
+fn record(frames: usize) -> (WavSink, Vec<u8>) {
+    let mut chip = common::boot(&[0x6005, 0xF018, 0x1204]);
+
+    let mut wav = WavSink::new(ToneGenerator::new(SAMPLE_RATE));
+    let mut scheduler = Scheduler::with_ipf(10);
+    let mut timers = Vec::new();
+    for _ in 0..frames {
+        scheduler.run_frame(&mut chip).unwrap();
+        timers.push(chip.sound_timer());
+        chip.play_sound(&mut wav);
+    }
+    (wav, timers)
+}
+
+#[test]
+fn wav_header_describes_16_bit_mono_pcm() {
+    let (wav, _) = record(10);
+    let mut b = Vec::new();
+    wav.write(&mut b).unwrap();
+
+    let data_len = wav.samples().len() as u32 * 2;
+    assert_eq!(&b[0..4], b"RIFF");
+    assert_eq!(le32(&b, 4), 36 + data_len);
+    assert_eq!(&b[8..12], b"WAVE");
+    assert_eq!(&b[12..16], b"fmt ");
+    assert_eq!(le32(&b, 16), 16);
+    assert_eq!(le16(&b, 20), 1);
+    assert_eq!(le16(&b, 22), 1);
+    assert_eq!(le32(&b, 24), SAMPLE_RATE);
+    assert_eq!(le32(&b, 28), SAMPLE_RATE * 2);
+    assert_eq!(le16(&b, 32), 2);
+    assert_eq!(le16(&b, 34), 16);
+    assert_eq!(&b[36..40], b"data");
+    assert_eq!(le32(&b, 40), data_len);
+    assert_eq!(b.len(), 44 + data_len as usize);
+}
+
+#[test]
+fn wav_sounds_only_while_the_sound_timer_runs() {
+    let (wav, timers) = record(10);
+    let frame = (SAMPLE_RATE / 60) as usize;
+    assert_eq!(wav.samples().len(), frame * 10);
+
+    for (n, (samples, &timer)) in wav.samples().chunks(frame).zip(timers.iter()).enumerate() {
+        let audible = samples.iter().any(|&s| s != 0);
+        assert_eq!(audible, timer > 0, "frame {} with the sound timer at {}", n, timer);
+    }
+    assert_eq!(timers.iter().filter(|&&t| t > 0).count(), 4);
+}
@@ -0,0 +1,43 @@
+//! Fixtures shared by the integration tests, each only uses some of them.
+
+#![allow(dead_code)]
+
+use chipate::{Chipate, Quirks};
+
+/// Opcodes as big endian ROM bytes.
+pub fn rom(ops: &[u16]) -> Vec<u8> {
+    ops.iter().flat_map(|&op| vec![(op >> 8) as u8, op as u8]).collect()
+}
+
+/// A fresh machine with nothing loaded, to restore states into.
+pub fn blank() -> Chipate<'static> {
+    let mut chip = Chipate::new();
+    chip.init();
+    chip
+}
+
+/// Loads `bytes` at 0x200 on a fresh machine.
+pub fn boot_bytes(bytes: &[u8]) -> Chipate<'static> {
+    let mut chip = blank();
+    chip.load_rom_from_bytes(bytes).unwrap();
+    chip
+}
+
+/// Loads `ops` at 0x200 on a fresh machine.
+pub fn boot(ops: &[u16]) -> Chipate<'static> {
+    boot_bytes(&rom(ops))
+}
+
+/// Loads `ops` at 0x200 on a fresh machine with `quirks`.
+pub fn boot_with(ops: &[u16], quirks: Quirks) -> Chipate<'static> {
+    let mut chip = boot(ops);
+    chip.set_quirks(quirks);
+    chip
+}
+
+/// Runs `n` instructions.
+pub fn step(chip: &mut Chipate, n: usize) {
+    for _ in 0..n {
+        chip.emulate_cycle().unwrap();
+    }
+}
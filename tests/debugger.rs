@@ -0,0 +1,153 @@
+//! Debugger commands and the watchpoints behind them.
+
+extern crate chipate;
+
+mod common;
+
+use chipate::watch::{self, Access, Compare, Target, Watchpoint};
+use chipate::{Chipate, Debugger, Quirks, Scheduler};
+use common::boot;
+
+fn read(target: Target) -> Access {
+    Access { target, write: false }
+}
+
+fn write(target: Target) -> Access {
+    Access { target, write: true }
+}
+
+/// Runs frames until the debugger pauses, at most `frames` of them.
+fn run_until_break(debugger: &mut Debugger, chip: &mut Chipate, frames: usize) -> bool {
+    let mut scheduler = Scheduler::with_ipf(10);
+    for _ in 0..frames {
+        if scheduler.run_frame_until(chip, |c| debugger.should_break(c)).unwrap() {
+            return true;
+        }
+    }
+    false
+}
+
+/// What the prompt prints on stopping, before asking for a command.
+fn announcement(debugger: &mut Debugger, chip: &mut Chipate) -> String {
+    let mut out = Vec::new();
+    debugger.prompt(chip, &mut &b""[..], &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn compare_parses_and_holds() {
+    let cases = [("==", 5, 5, true), ("!=", 5, 5, false), ("<", 4, 5, true), ("<=", 5, 5, true), (">", 5, 5, false),
+                 (">=", 6, 5, true)];
+    for &(op, a, b, holds) in &cases {
+        let c = Compare::parse(op).unwrap();
+        assert_eq!(c.holds(a, b), holds, "{} {} {}", a, op, b);
+    }
+    assert_eq!(Compare::parse("="), None);
+}
+
+#[test]
+fn targets_parse() {
+    assert_eq!(Target::parse("v3"), Ok(Target::V(3)));
+    assert_eq!(Target::parse("VF"), Ok(Target::V(15)));
+    assert_eq!(Target::parse("i"), Ok(Target::I));
+    assert_eq!(Target::parse("dt"), Ok(Target::DelayTimer));
+    assert_eq!(Target::parse("ST"), Ok(Target::SoundTimer));
+    assert_eq!(Target::parse("0x300"), Ok(Target::Memory(0x300)));
+    assert_eq!(Target::parse("2a0"), Ok(Target::Memory(0x2A0)));
+    assert!(Target::parse("VX").is_err());
+}
+
+#[test]
+fn accesses_follow_the_opcode_and_quirks() {
+    let chip = boot(&[0x8126]);
+    assert_eq!(watch::accesses(&chip), [read(Target::V(2)), write(Target::V(1)), write(Target::V(15))]);
+
+    let mut chip = boot(&[0x8126]);
+    chip.set_quirks(Quirks::superchip());
+    assert_eq!(watch::accesses(&chip), [read(Target::V(1)), write(Target::V(1)), write(Target::V(15))]);
+
+    let mut chip = boot(&[0xA300, 0xF133]);
+    chip.emulate_cycle().unwrap();
+    assert_eq!(watch::accesses(&chip),
+               [read(Target::V(1)),
+                read(Target::I),
+                write(Target::Memory(0x300)),
+                write(Target::Memory(0x301)),
+                write(Target::Memory(0x302))]);
+}
+
+#[test]
+fn watchpoints_match_kind_and_condition() {
+    let mut chip = boot(&[0x6310]);
+    chip.emulate_cycle().unwrap();
+
+    let w = Watchpoint::write(Target::V(3));
+    assert!(w.triggered_by(&write(Target::V(3)), &chip));
+    assert!(!w.triggered_by(&read(Target::V(3)), &chip));
+    assert!(!w.triggered_by(&write(Target::V(4)), &chip));
+    assert!(Watchpoint::read(Target::V(3)).triggered_by(&read(Target::V(3)), &chip));
+    assert!(Watchpoint::access(Target::V(3)).triggered_by(&read(Target::V(3)), &chip));
+
+    assert!(w.when(Compare::Eq, 0x10).triggered_by(&write(Target::V(3)), &chip));
+    assert!(!w.when(Compare::Lt, 0x10).triggered_by(&write(Target::V(3)), &chip));
+}
+
+#[test]
+fn break_and_delete_commands() {
+    let mut chip = boot(&[0x6001, 0x6102, 0x1204]);
+    let mut debugger = Debugger::new();
+    assert_eq!(debugger.execute(&mut chip, "b 204"), Ok("Breakpoint at 0x204".to_string()));
+    assert_eq!(debugger.execute(&mut chip, "break 0x202"), Ok("Breakpoint at 0x202".to_string()));
+    assert_eq!(debugger.breakpoints(), [0x202, 0x204]);
+    assert!(debugger.execute(&mut chip, "b").is_err());
+    assert!(debugger.execute(&mut chip, "b zz").is_err());
+
+    assert!(debugger.execute(&mut chip, "d 202").is_ok());
+    assert_eq!(debugger.execute(&mut chip, "delete 202"), Err("No breakpoint at 0x202".to_string()));
+
+    debugger.execute(&mut chip, "c").unwrap();
+    assert!(run_until_break(&mut debugger, &mut chip, 1));
+    assert_eq!(chip.pc(), 0x204);
+    assert_eq!(chip.v()[1], 2);
+}
+
+#[test]
+fn watch_commands() {
+    let mut chip = boot(&[0x6001]);
+    let mut debugger = Debugger::new();
+    assert_eq!(debugger.execute(&mut chip, "watch v3 == 10"),
+               Ok("Watchpoint 1: write V3 == 0x10".to_string()));
+    assert_eq!(debugger.execute(&mut chip, "rwatch i"), Ok("Watchpoint 2: read I".to_string()));
+    assert_eq!(debugger.execute(&mut chip, "awatch 300"), Ok("Watchpoint 3: access 0x300".to_string()));
+    assert_eq!(debugger.watchpoints()[0], Watchpoint::write(Target::V(3)).when(Compare::Eq, 0x10));
+    assert!(debugger.execute(&mut chip, "watch").is_err());
+    assert!(debugger.execute(&mut chip, "watch v3 =< 10").is_err());
+    assert!(debugger.execute(&mut chip, "watch v3 ==").is_err());
+
+    assert_eq!(debugger.execute(&mut chip, "unwatch 2"), Ok("Removed watchpoint 2: read I".to_string()));
+    assert_eq!(debugger.execute(&mut chip, "unwatch 5"), Err("No watchpoint 5".to_string()));
+    assert_eq!(debugger.watchpoints().len(), 2);
+}
+
+#[test]
+fn step_stops_on_a_watchpoint() {
+    let mut chip = boot(&[0x6001, 0x6310, 0x6002]);
+    let mut debugger = Debugger::new();
+    debugger.execute(&mut chip, "watch v3").unwrap();
+    let reply = debugger.execute(&mut chip, "step 3").unwrap();
+    assert!(reply.starts_with("Watchpoint 1: V3 written by 0x202, now 0x10"), "{}", reply);
+    assert_eq!(chip.pc(), 0x204);
+}
+
+#[test]
+fn watching_a_timer_stops_when_it_runs_out() {
+    // DT := 3, then spin
+    let mut chip = boot(&[0x6003, 0xF015, 0x1204]);
+    let mut debugger = Debugger::new();
+    debugger.execute(&mut chip, "watch dt == 0").unwrap();
+    debugger.execute(&mut chip, "c").unwrap();
+
+    assert!(run_until_break(&mut debugger, &mut chip, 10));
+    assert_eq!(chip.delay_timer(), 0);
+    assert!(announcement(&mut debugger, &mut chip).starts_with("Watchpoint 1: DT counted down, now 0x0"));
+}
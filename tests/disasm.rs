@@ -0,0 +1,88 @@
+//! The disassembler on whole ROMs, and back through the assembler.
+
+extern crate chipate;
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use chipate::asm;
+use chipate::disasm::{self, Listing};
+use common::rom;
+
+/// Calls a subroutine, draws a sprite and spins. The sprite bytes after
+/// the code decode as opcodes too, but nothing reaches them.
+static PROGRAM: &[u16] = &[
+    0x220A, // 200 :call sub_20A
+    0xA20E, // 202 i := data_20E
+    0x3001, // 204 if v0 != 1 then
+    0xD015, // 206 sprite v0 v1 5
+    0x1208, // 208 jump label_208
+    0x6001, // 20A v0 := 1
+    0x00EE, // 20C return
+    0x6090, // 20E sprite data
+    0x9060, // 210
+];
+
+#[test]
+fn single_opcodes() {
+    assert_eq!(disasm::disassemble(0x8126, None), Some("v1 >>= v2".to_string()));
+    assert_eq!(disasm::disassemble(0xF000, Some(0x1234)), Some("i := long 0x1234".to_string()));
+    assert_eq!(disasm::disassemble(0x5121, None), None);
+}
+
+#[test]
+fn only_reachable_instructions_are_code() {
+    let bytes = rom(PROGRAM);
+    let listing = Listing::new(&bytes, 0x200);
+    for addr in (0x200..0x20E).step_by(2) {
+        assert!(listing.is_code(addr), "0x{:03X} should be code", addr);
+    }
+    assert!(!listing.is_code(0x20E));
+    assert!(!listing.is_code(0x210));
+}
+
+#[test]
+fn labels_name_what_points_at_them() {
+    let bytes = rom(PROGRAM);
+    let octo = Listing::new(&bytes, 0x200).to_octo();
+    for label in &[": main", ": sub_20A", ": data_20E", ": label_208"] {
+        assert!(octo.contains(label), "{} missing from\n{}", label, octo);
+    }
+    assert!(octo.contains(":call sub_20A"));
+    assert!(octo.contains("i := data_20E"));
+    assert!(octo.contains("jump label_208"));
+    assert!(octo.contains("0x60 0x90 0x90 0x60"));
+}
+
+#[test]
+fn assemble_disassemble_assemble_is_byte_exact() {
+    let hex_digits = fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/roms/hex_digits.8o")).unwrap();
+    let sources = [hex_digits.as_str(),
+                   ": main
+                        v3 := 7
+                        loop
+                            if v3 == 2 begin
+                                i := long glyph
+                            else
+                                sub_draw
+                            end
+                            v3 += -1
+                            while v3 != 0
+                        again
+                        loop again
+                    : sub_draw
+                        i := glyph
+                        sprite v0 v0 4
+                        return
+                    : glyph
+                        0x18 0x24 0x24 0x18"];
+
+    for source in &sources {
+        let first = asm::assemble(source).unwrap();
+        let octo = Listing::new(&first, 0x200).to_octo();
+        let second = asm::assemble(&octo).unwrap_or_else(|e| panic!("{} in\n{}", e, octo));
+        assert_eq!(first, second, "round trip through\n{}", octo);
+    }
+}
@@ -0,0 +1,128 @@
+//! Screen dumps encoded without a window.
+
+extern crate chipate;
+
+mod common;
+
+use chipate::dump;
+use chipate::Chipate;
+
+/// Draws the font zero in the top left corner.
+fn zero() -> Chipate<'static> {
+    let mut chip = common::boot(&[0xF029, 0xD005, 0x1204]);
+    common::step(&mut chip, 3);
+    chip
+}
+
+fn be32(b: &[u8], at: usize) -> u32 {
+    (b[at] as u32) << 24 | (b[at + 1] as u32) << 16 | (b[at + 2] as u32) << 8 | b[at + 3] as u32
+}
+
+/// The bitwise CRC-32 PNG asks for, written out again to check the encoder's.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb88320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+/// Splits a PNG into its chunks, checking every CRC on the way.
+fn chunks(png: &[u8]) -> Vec<(String, Vec<u8>)> {
+    let mut out = Vec::new();
+    let mut at = 8;
+    while at < png.len() {
+        let len = be32(png, at) as usize;
+        let body = &png[at + 4..at + 8 + len];
+        let kind = String::from_utf8(body[..4].to_vec()).unwrap();
+        assert_eq!(be32(png, at + 8 + len), crc32(body), "{} CRC", kind);
+        out.push((kind, body[4..].to_vec()));
+        at += 12 + len;
+    }
+    out
+}
+
+/// Undoes a zlib stream made of stored deflate blocks.
+fn inflate_stored(z: &[u8]) -> Vec<u8> {
+    assert_eq!((z[0] as u16) << 8 | z[1] as u16, 0x7801);
+    let mut out = Vec::new();
+    let mut at = 2;
+    loop {
+        let last = z[at] & 1 != 0;
+        assert_eq!(z[at] >> 1, 0, "not a stored block");
+        let len = z[at + 1] as usize | (z[at + 2] as usize) << 8;
+        let nlen = z[at + 3] as usize | (z[at + 4] as usize) << 8;
+        assert_eq!(len ^ 0xffff, nlen);
+        out.extend_from_slice(&z[at + 5..at + 5 + len]);
+        at += 5 + len;
+        if last {
+            break;
+        }
+    }
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in &out {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    assert_eq!(be32(z, at), b << 16 | a, "Adler-32");
+    assert_eq!(z.len(), at + 4);
+    out
+}
+
+#[test]
+fn png_chunks_and_header() {
+    let png = dump::png(&zero().display);
+    assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+
+    let chunks = chunks(&png);
+    let kinds: Vec<&str> = chunks.iter().map(|c| c.0.as_str()).collect();
+    assert_eq!(kinds, ["IHDR", "IDAT", "IEND"]);
+
+    let ihdr = &chunks[0].1;
+    assert_eq!(ihdr.len(), 13);
+    assert_eq!(be32(ihdr, 0), 64);
+    assert_eq!(be32(ihdr, 4), 32);
+    assert_eq!(&ihdr[8..], [8, 0, 0, 0, 0]);
+    assert!(chunks[2].1.is_empty());
+    assert_eq!(&png[png.len() - 4..], [0xAE, 0x42, 0x60, 0x82]);
+}
+
+#[test]
+fn png_scanlines_match_the_framebuffer() {
+    let chip = zero();
+    let raw = inflate_stored(&chunks(&dump::png(&chip.display))[1].1);
+    assert_eq!(raw.len(), 32 * 65);
+
+    for (y, line) in raw.chunks(65).enumerate() {
+        assert_eq!(line[0], 0, "filter of row {}", y);
+        let want: Vec<u8> = chip.display.gfx()[y][..64].iter().map(|&p| if p != 0 { 255 } else { 0 }).collect();
+        assert_eq!(&line[1..], &want[..], "row {}", y);
+    }
+    assert_eq!(&raw[1..9], [255, 255, 255, 255, 0, 0, 0, 0]);
+    assert_eq!(&raw[66..74], [255, 0, 0, 255, 0, 0, 0, 0]);
+}
+
+#[test]
+fn pbm_header_and_rows() {
+    let pbm = String::from_utf8(dump::pbm(&zero().display)).unwrap();
+    let lines: Vec<&str> = pbm.lines().collect();
+    assert_eq!(lines[..2], ["P1", "64 32"]);
+    assert_eq!(lines.len(), 2 + 32);
+
+    let bits = |row: &str| -> String { row.split(' ').collect() };
+    assert_eq!(bits(lines[2]), format!("11110000{}", "0".repeat(56)));
+    assert_eq!(bits(lines[3]), format!("10010000{}", "0".repeat(56)));
+    assert_eq!(bits(lines[6]), format!("11110000{}", "0".repeat(56)));
+    assert_eq!(bits(lines[7]), "0".repeat(64));
+}
+
+#[test]
+fn format_follows_the_extension() {
+    assert_eq!(dump::Format::from_path("shot.PNG"), dump::Format::Png);
+    assert_eq!(dump::Format::from_path("shot.pbm"), dump::Format::Pbm);
+    assert_eq!(dump::Format::from_path("shot.txt"), dump::Format::Ascii);
+    assert!(dump::ascii(&zero().display).starts_with("####...."));
+}
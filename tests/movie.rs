@@ -0,0 +1,75 @@
+//! Recording a run and playing it back.
+
+extern crate chipate;
+
+mod common;
+
+use chipate::movie::Frame;
+use chipate::{Chipate, EmuError, InputSource, Movie, Scheduler};
+use common::boot_bytes;
+
+static HZ: u32 = 600;
+
+/// Adds random numbers into V1 and counts the frames key 0 is up in V3.
+static ROM: &[u8] = &[0xC0, 0xFF, 0x81, 0x04, 0xE2, 0x9E, 0x73, 0x01, 0x12, 0x00];
+
+/// Holds key 0 down on even frames.
+struct EveryOther(bool);
+
+impl InputSource for EveryOther {
+    fn poll(&mut self, key: &mut [u8; 16]) {
+        key[0] = self.0 as u8;
+        self.0 = !self.0;
+    }
+}
+
+fn record(frames: usize) -> (Movie, Chipate<'static>) {
+    let mut chip = boot_bytes(ROM);
+    let mut movie = Movie::record(&mut chip, HZ);
+    let mut scheduler = Scheduler::with_hz(HZ);
+    let mut input = EveryOther(true);
+    for _ in 0..frames {
+        chip.set_keys(&mut input);
+        scheduler.run_frame(&mut chip).unwrap();
+        movie.record_frame(&mut chip);
+    }
+    (movie, chip)
+}
+
+#[test]
+fn replay_matches_the_recording() {
+    let (movie, recorded) = record(30);
+    let movie = Movie::from_bytes(&movie.to_bytes()).unwrap();
+    assert_eq!(movie.frames.len(), 30);
+    assert!(movie.frames.iter().all(|f| !f.random.is_empty()));
+
+    let mut chip = common::blank();
+    movie.setup(&mut chip);
+    chip.load_rom_from_bytes(ROM).unwrap();
+    movie.play(&mut chip).unwrap();
+
+    assert_eq!(chip.v(), recorded.v());
+    assert_eq!(chip.pc(), recorded.pc());
+    assert!(recorded.v()[3] > 0);
+}
+
+#[test]
+fn frames_with_many_random_bytes_read_back() {
+    let (mut movie, _) = record(1);
+    movie.frames.push(Frame {
+        keys: [1; 16],
+        random: (0..70000).map(|n| n as u8).collect(),
+    });
+    assert_eq!(Movie::from_bytes(&movie.to_bytes()).unwrap(), movie);
+}
+
+#[test]
+fn refuses_a_different_rom() {
+    let (movie, _) = record(5);
+    let mut chip = boot_bytes(&[0x12, 0x00]);
+    match movie.play(&mut chip) {
+        Err(EmuError::InvalidMovie(reason)) => assert_eq!(reason, "it was recorded with a different ROM"),
+        r => panic!("expected a different ROM to be refused, got {:?}", r.err()),
+    }
+    assert!(movie.check_rom(&boot_bytes(ROM)).is_ok());
+}
@@ -0,0 +1,570 @@
+//! Every opcode handler, run through `emulate_cycle` on a headless machine.
+
+extern crate chipate;
+
+mod common;
+
+use chipate::rng::SequenceRng;
+use chipate::{AudioSink, Chipate, EmuError, InputSource, Quirks};
+use common::{boot, step};
+
+/// Boots `ops` and runs them all, for straight line code.
+fn run(ops: &[u16]) -> Chipate<'static> {
+    let mut chip = boot(ops);
+    step(&mut chip, ops.len());
+    chip
+}
+
+fn pixel(chip: &Chipate, x: usize, y: usize) -> u8 {
+    chip.display.gfx()[y][x]
+}
+
+struct Keys([u8; 16]);
+
+impl InputSource for Keys {
+    fn poll(&mut self, key: &mut [u8; 16]) {
+        *key = self.0;
+    }
+}
+
+fn press(chip: &mut Chipate, k: usize) {
+    let mut keys = [0; 16];
+    keys[k] = 1;
+    chip.set_keys(&mut Keys(keys));
+}
+
+#[derive(Default)]
+struct Audio {
+    tone: bool,
+    pattern: Option<([u8; 16], u8)>,
+}
+
+impl AudioSink for Audio {
+    fn set_tone(&mut self, on: bool) {
+        self.tone = on;
+    }
+
+    fn set_pattern(&mut self, pattern: &[u8; 16], pitch: u8) {
+        self.pattern = Some((*pattern, pitch));
+    }
+}
+
+// Display
+
+#[test]
+fn _00e0_clears_the_screen() {
+    // V0 = 0, I = font 0, draw, clear
+    let chip = run(&[0xA000, 0xD005, 0x00E0]);
+    assert!(chip.display.gfx().iter().all(|row| row.iter().all(|&p| p == 0)));
+}
+
+#[test]
+fn _dxyn_draws_and_reports_collisions() {
+    let mut chip = boot(&[0x6001, 0x6102, 0xA000, 0xD015, 0xD015]);
+    step(&mut chip, 4);
+    // Font 0 starts with 0xF0
+    assert_eq!((1..5).map(|x| pixel(&chip, x, 2)).collect::<Vec<_>>(), vec![1, 1, 1, 1]);
+    assert_eq!(pixel(&chip, 5, 2), 0);
+    assert_eq!(chip.v()[0xF], 0);
+
+    step(&mut chip, 1);
+    assert_eq!(pixel(&chip, 1, 2), 0);
+    assert_eq!(chip.v()[0xF], 1);
+}
+
+#[test]
+fn _dxyn_wraps_the_origin_and_clips_or_wraps_the_rest() {
+    // Font 0 at (62, 30) plus one full screen, which wraps to (62, 30)
+    let ops = [0x607E, 0x615E, 0xA000, 0xD015];
+
+    let mut chip = boot(&ops);
+    chip.set_quirks(Quirks::cosmac_vip());
+    step(&mut chip, 4);
+    assert_eq!(pixel(&chip, 62, 30), 1);
+    assert_eq!(pixel(&chip, 0, 30), 0);
+    assert_eq!(pixel(&chip, 62, 0), 0);
+
+    let mut chip = boot(&ops);
+    chip.set_quirks(Quirks::xochip());
+    step(&mut chip, 4);
+    assert_eq!(pixel(&chip, 62, 30), 1);
+    assert_eq!(pixel(&chip, 0, 30), 1);
+    assert_eq!(pixel(&chip, 62, 0), 1);
+}
+
+#[test]
+fn _dxy0_draws_a_16x16_sprite() {
+    // 32 bytes of 0xFF after the code
+    let mut ops = vec![0x00FF, 0xA20A, 0x6000, 0xD000, 0x1208];
+    ops.extend_from_slice(&[0xFFFF; 16]);
+    let mut chip = boot(&ops);
+    step(&mut chip, 4);
+    assert_eq!(pixel(&chip, 15, 15), 1);
+    assert_eq!(pixel(&chip, 16, 15), 0);
+    assert_eq!(pixel(&chip, 15, 16), 0);
+}
+
+#[test]
+fn _00cn_00dn_00fb_00fc_scroll() {
+    let setup = [0xA000, 0x6008, 0x6108, 0xD011];
+
+    let down = run(&[setup[0], setup[1], setup[2], setup[3], 0x00C3]);
+    assert_eq!(pixel(&down, 8, 11), 1);
+    assert_eq!(pixel(&down, 8, 8), 0);
+
+    let up = run(&[setup[0], setup[1], setup[2], setup[3], 0x00D3]);
+    assert_eq!(pixel(&up, 8, 5), 1);
+    assert_eq!(pixel(&up, 8, 8), 0);
+
+    let right = run(&[setup[0], setup[1], setup[2], setup[3], 0x00FB]);
+    assert_eq!(pixel(&right, 12, 8), 1);
+    assert_eq!(pixel(&right, 8, 8), 0);
+
+    let left = run(&[setup[0], setup[1], setup[2], setup[3], 0x00FC]);
+    assert_eq!(pixel(&left, 4, 8), 1);
+    assert_eq!(pixel(&left, 8, 8), 0);
+}
+
+#[test]
+fn _00fe_00ff_switch_resolution() {
+    let mut chip = boot(&[0x00FF, 0x00FE]);
+    step(&mut chip, 1);
+    assert!(chip.display.hires());
+    assert_eq!((chip.display.width(), chip.display.height()), (128, 64));
+    step(&mut chip, 1);
+    assert!(!chip.display.hires());
+    assert_eq!((chip.display.width(), chip.display.height()), (64, 32));
+}
+
+#[test]
+fn _fn01_selects_planes() {
+    let mut chip = boot(&[0xF201, 0xA000, 0xD005]);
+    chip.set_xochip(true);
+    step(&mut chip, 3);
+    assert_eq!(chip.display.planes(), 2);
+    assert_eq!(pixel(&chip, 0, 0), 2);
+}
+
+// Flow
+
+#[test]
+fn _00fd_exits() {
+    let mut chip = boot(&[0x00FD, 0x6001]);
+    step(&mut chip, 2);
+    assert!(chip.exited());
+    assert_eq!(chip.v()[0], 0);
+}
+
+#[test]
+fn _0nnn_is_ignored() {
+    let chip = run(&[0x0123]);
+    assert_eq!(chip.pc(), 0x202);
+}
+
+#[test]
+fn _1nnn_jumps() {
+    let chip = run(&[0x1345]);
+    assert_eq!(chip.pc(), 0x345);
+}
+
+#[test]
+fn _2nnn_00ee_call_and_return() {
+    // 0x200 call 0x206, 0x206 return
+    let mut chip = boot(&[0x2206, 0x6001, 0x1204, 0x00EE]);
+    step(&mut chip, 1);
+    assert_eq!(chip.pc(), 0x206);
+    assert_eq!(chip.stack(), &[0x200]);
+    step(&mut chip, 1);
+    assert_eq!(chip.pc(), 0x202);
+    assert!(chip.stack().is_empty());
+}
+
+#[test]
+fn _00ee_with_an_empty_stack_underflows() {
+    let mut chip = boot(&[0x00EE]);
+    match chip.emulate_cycle() {
+        Err(EmuError::StackUnderflow { pc: 0x200, opcode: 0x00EE }) => {}
+        r => panic!("expected a stack underflow, got {:?}", r),
+    }
+}
+
+#[test]
+fn _2nnn_past_16_levels_overflows() {
+    let mut chip = boot(&[0x2200]);
+    step(&mut chip, 16);
+    match chip.emulate_cycle() {
+        Err(EmuError::StackOverflow { pc: 0x200, opcode: 0x2200 }) => {}
+        r => panic!("expected a stack overflow, got {:?}", r),
+    }
+}
+
+#[test]
+fn _bnnn_jumps_with_an_offset() {
+    let mut chip = boot(&[0x6010, 0x6220, 0xB300]);
+    chip.set_quirks(Quirks::cosmac_vip());
+    step(&mut chip, 3);
+    assert_eq!(chip.pc(), 0x310);
+
+    let mut chip = boot(&[0x6010, 0x6320, 0xB300]);
+    chip.set_quirks(Quirks::superchip());
+    step(&mut chip, 3);
+    assert_eq!(chip.pc(), 0x320);
+}
+
+// Conditions
+
+#[test]
+fn _3xnn_skips_when_equal() {
+    assert_eq!(run(&[0x6005, 0x3005]).pc(), 0x206);
+    assert_eq!(run(&[0x6005, 0x3006]).pc(), 0x204);
+}
+
+#[test]
+fn _4xnn_skips_when_not_equal() {
+    assert_eq!(run(&[0x6005, 0x4005]).pc(), 0x204);
+    assert_eq!(run(&[0x6005, 0x4006]).pc(), 0x206);
+}
+
+#[test]
+fn _5xy0_skips_when_registers_are_equal() {
+    assert_eq!(run(&[0x6005, 0x6105, 0x5010]).pc(), 0x208);
+    assert_eq!(run(&[0x6005, 0x6106, 0x5010]).pc(), 0x206);
+}
+
+#[test]
+fn _9xy0_skips_when_registers_differ() {
+    assert_eq!(run(&[0x6005, 0x6105, 0x9010]).pc(), 0x206);
+    assert_eq!(run(&[0x6005, 0x6106, 0x9010]).pc(), 0x208);
+}
+
+#[test]
+fn skips_step_over_f000_nnnn_whole() {
+    let mut chip = boot(&[0x3000, 0xF000, 0x1234]);
+    step(&mut chip, 1);
+    assert_eq!(chip.pc(), 0x206);
+}
+
+#[test]
+fn _ex9e_exa1_test_keys() {
+    let mut chip = boot(&[0x6007, 0xE09E]);
+    press(&mut chip, 7);
+    step(&mut chip, 2);
+    assert_eq!(chip.pc(), 0x206);
+
+    let mut chip = boot(&[0x6007, 0xE09E]);
+    press(&mut chip, 6);
+    step(&mut chip, 2);
+    assert_eq!(chip.pc(), 0x204);
+
+    let mut chip = boot(&[0x6007, 0xE0A1]);
+    press(&mut chip, 7);
+    step(&mut chip, 2);
+    assert_eq!(chip.pc(), 0x204);
+
+    let mut chip = boot(&[0x6007, 0xE0A1]);
+    step(&mut chip, 2);
+    assert_eq!(chip.pc(), 0x206);
+}
+
+#[test]
+fn _fx0a_waits_for_a_key() {
+    let mut chip = boot(&[0xF30A]);
+    step(&mut chip, 5);
+    assert_eq!(chip.pc(), 0x200);
+
+    // Held down, it is latched but not taken yet
+    press(&mut chip, 0xC);
+    step(&mut chip, 2);
+    assert_eq!(chip.pc(), 0x200);
+
+    chip.set_keys(&mut Keys([0; 16]));
+    step(&mut chip, 1);
+    assert_eq!(chip.pc(), 0x202);
+    assert_eq!(chip.v()[3], 0xC);
+}
+
+// Registers
+
+#[test]
+fn _6xnn_7xnn_set_and_add_without_carry() {
+    let chip = run(&[0x6AFF, 0x7A02, 0x6F00]);
+    assert_eq!(chip.v()[0xA], 0x01);
+
+    let chip = run(&[0x6F00, 0x6AFF, 0x7A02]);
+    assert_eq!(chip.v()[0xF], 0);
+}
+
+#[test]
+fn _8xy0_copies() {
+    assert_eq!(run(&[0x6142, 0x8010]).v()[0], 0x42);
+}
+
+#[test]
+fn _8xy1_8xy2_8xy3_bit_ops_and_the_vf_reset_quirk() {
+    let ops = |op| [0x6F07, 0x600C, 0x610A, op];
+    for &(op, result) in &[(0x8011, 0x0E), (0x8012, 0x08), (0x8013, 0x06)] {
+        let mut chip = boot(&ops(op));
+        chip.set_quirks(Quirks::cosmac_vip());
+        step(&mut chip, 4);
+        assert_eq!(chip.v()[0], result, "{:04X}", op);
+        assert_eq!(chip.v()[0xF], 0, "{:04X}", op);
+
+        let mut chip = boot(&ops(op));
+        chip.set_quirks(Quirks::superchip());
+        step(&mut chip, 4);
+        assert_eq!(chip.v()[0], result, "{:04X}", op);
+        assert_eq!(chip.v()[0xF], 7, "{:04X}", op);
+    }
+}
+
+#[test]
+fn _8xy4_sets_vf_on_carry() {
+    let chip = run(&[0x60FF, 0x6101, 0x8014]);
+    assert_eq!((chip.v()[0], chip.v()[0xF]), (0x00, 1));
+
+    let chip = run(&[0x60FE, 0x6101, 0x6F01, 0x8014]);
+    assert_eq!((chip.v()[0], chip.v()[0xF]), (0xFF, 0));
+
+    let chip = run(&[0x6080, 0x6180, 0x8014]);
+    assert_eq!((chip.v()[0], chip.v()[0xF]), (0x00, 1));
+}
+
+#[test]
+fn _8xy5_clears_vf_on_borrow() {
+    let chip = run(&[0x6005, 0x6103, 0x8015]);
+    assert_eq!((chip.v()[0], chip.v()[0xF]), (0x02, 1));
+
+    let chip = run(&[0x6003, 0x6105, 0x8015]);
+    assert_eq!((chip.v()[0], chip.v()[0xF]), (0xFE, 0));
+
+    // No borrow when equal
+    let chip = run(&[0x6005, 0x6105, 0x8015]);
+    assert_eq!((chip.v()[0], chip.v()[0xF]), (0x00, 1));
+}
+
+#[test]
+fn _8xy7_clears_vf_on_borrow() {
+    let chip = run(&[0x6003, 0x6105, 0x8017]);
+    assert_eq!((chip.v()[0], chip.v()[0xF]), (0x02, 1));
+
+    let chip = run(&[0x6005, 0x6103, 0x8017]);
+    assert_eq!((chip.v()[0], chip.v()[0xF]), (0xFE, 0));
+
+    let chip = run(&[0x6005, 0x6105, 0x8017]);
+    assert_eq!((chip.v()[0], chip.v()[0xF]), (0x00, 1));
+}
+
+#[test]
+fn arithmetic_on_vf_keeps_the_flag() {
+    // VF = 0xFF + 0x01, the carry wins over the sum
+    let chip = run(&[0x6FFF, 0x6101, 0x8F14]);
+    assert_eq!(chip.v()[0xF], 1);
+
+    // VF = 0x01 - 0x02, the borrow wins over the difference
+    let chip = run(&[0x6F01, 0x6102, 0x8F15]);
+    assert_eq!(chip.v()[0xF], 0);
+
+    let chip = run(&[0x6F02, 0x6101, 0x8F17]);
+    assert_eq!(chip.v()[0xF], 0);
+}
+
+#[test]
+fn _8xy6_8xye_shift_with_and_without_the_shift_vy_quirk() {
+    let mut chip = boot(&[0x6003, 0x6181, 0x8016]);
+    chip.set_quirks(Quirks::cosmac_vip());
+    step(&mut chip, 3);
+    assert_eq!((chip.v()[0], chip.v()[0xF]), (0x40, 1));
+
+    let mut chip = boot(&[0x6003, 0x6181, 0x8016]);
+    chip.set_quirks(Quirks::superchip());
+    step(&mut chip, 3);
+    assert_eq!((chip.v()[0], chip.v()[0xF]), (0x01, 1));
+
+    let mut chip = boot(&[0x6003, 0x6181, 0x801E]);
+    chip.set_quirks(Quirks::cosmac_vip());
+    step(&mut chip, 3);
+    assert_eq!((chip.v()[0], chip.v()[0xF]), (0x02, 1));
+
+    let mut chip = boot(&[0x6003, 0x6181, 0x801E]);
+    chip.set_quirks(Quirks::superchip());
+    step(&mut chip, 3);
+    assert_eq!((chip.v()[0], chip.v()[0xF]), (0x06, 0));
+}
+
+#[test]
+fn _cxnn_masks_the_random_byte() {
+    let mut chip = boot(&[0xC00F, 0xC1FF, 0xC200]);
+    chip.set_rng(Box::new(SequenceRng::new(&[0xAB, 0xCD, 0xEF])));
+    step(&mut chip, 3);
+    assert_eq!(&chip.v()[..3], &[0x0B, 0xCD, 0x00]);
+}
+
+// Memory
+
+#[test]
+fn _annn_sets_i() {
+    assert_eq!(run(&[0xA123]).i(), 0x123);
+}
+
+#[test]
+fn _f000_sets_a_16_bit_i() {
+    let mut chip = boot(&[0xF000, 0xBEEF, 0x6001]);
+    chip.set_xochip(true);
+    step(&mut chip, 1);
+    assert_eq!(chip.i(), 0xBEEF);
+    assert_eq!(chip.pc(), 0x204);
+}
+
+#[test]
+fn _fx1e_adds_to_i() {
+    assert_eq!(run(&[0xA0FF, 0x6002, 0xF01E]).i(), 0x101);
+}
+
+#[test]
+fn _fx29_fx30_point_at_the_fonts() {
+    let chip = run(&[0x600A, 0xF029]);
+    assert_eq!(chip.i(), 50);
+    assert_eq!(&chip.memory()[50..55], &[0xF0, 0x90, 0xF0, 0x90, 0x90]);
+
+    let chip = run(&[0x6001, 0xF030]);
+    let big = chip.i() as usize;
+    assert_eq!(chip.i(), run(&[0x6000, 0xF030]).i() + 10);
+    assert!(chip.memory()[big..big + 10].iter().any(|&b| b != 0));
+}
+
+#[test]
+fn _fx33_stores_bcd() {
+    for &(value, digits) in &[(0u8, [0, 0, 0]), (9, [0, 0, 9]), (10, [0, 1, 0]), (99, [0, 9, 9]), (100, [1, 0, 0]),
+                              (255, [2, 5, 5])] {
+        let chip = run(&[0xA300, 0x6000 | value as u16, 0xF033]);
+        assert_eq!(&chip.memory()[0x300..0x303], &digits, "{}", value);
+        assert_eq!(chip.i(), 0x300);
+    }
+}
+
+#[test]
+fn _fx33_past_the_end_of_memory_fails() {
+    let mut chip = boot(&[0xAFFE, 0xF033]);
+    step(&mut chip, 1);
+    match chip.emulate_cycle() {
+        Err(EmuError::MemoryOutOfBounds { addr: 0x1000, .. }) => {}
+        r => panic!("expected an out of bounds access, got {:?}", r),
+    }
+}
+
+#[test]
+fn _fx55_fx65_with_and_without_the_increment_i_quirk() {
+    let ops = [0xA300, 0x6011, 0x6122, 0x6233, 0xF255, 0xA300, 0x6000, 0x6100, 0xF165];
+
+    let mut chip = boot(&ops);
+    chip.set_quirks(Quirks::cosmac_vip());
+    step(&mut chip, 5);
+    assert_eq!(&chip.memory()[0x300..0x304], &[0x11, 0x22, 0x33, 0x00]);
+    assert_eq!(chip.i(), 0x303);
+    step(&mut chip, 4);
+    assert_eq!(&chip.v()[..3], &[0x11, 0x22, 0x33]);
+    assert_eq!(chip.i(), 0x302);
+
+    let mut chip = boot(&ops);
+    chip.set_quirks(Quirks::superchip());
+    step(&mut chip, ops.len());
+    assert_eq!(&chip.v()[..3], &[0x11, 0x22, 0x33]);
+    assert_eq!(chip.i(), 0x300);
+}
+
+#[test]
+fn _fx55_fx65_increment_i_wraps_at_the_top_of_64k() {
+    let mut chip = boot(&[0xF000, 0xFFFF, 0x6042, 0xF055, 0xF000, 0xFFFF, 0x6000, 0xF065]);
+    chip.set_quirks(Quirks::xochip());
+    chip.set_xochip(true);
+    step(&mut chip, 3);
+    assert_eq!(chip.memory()[0xFFFF], 0x42);
+    assert_eq!(chip.i(), 0);
+
+    step(&mut chip, 3);
+    assert_eq!(chip.v()[0], 0x42);
+    assert_eq!(chip.i(), 0);
+}
+
+#[test]
+fn _5xy2_5xy3_save_and_load_ranges() {
+    let mut chip = boot(&[0xA300, 0x6111, 0x6222, 0x6333, 0x5132, 0x5312, 0x6100, 0x6200, 0x6300, 0x5133]);
+    chip.set_xochip(true);
+    step(&mut chip, 5);
+    assert_eq!(&chip.memory()[0x300..0x303], &[0x11, 0x22, 0x33]);
+    assert_eq!(chip.i(), 0x300);
+
+    // Reversed, V3 first
+    step(&mut chip, 1);
+    assert_eq!(&chip.memory()[0x300..0x303], &[0x33, 0x22, 0x11]);
+
+    step(&mut chip, 4);
+    assert_eq!(&chip.v()[1..4], &[0x33, 0x22, 0x11]);
+}
+
+#[test]
+fn _fx75_fx85_keep_rpl_flags() {
+    let chip = run(&[0x6011, 0x6122, 0xF175, 0x6000, 0x6100, 0xF185]);
+    assert_eq!(&chip.rpl_flags()[..3], &[0x11, 0x22, 0x00]);
+    assert_eq!(&chip.v()[..2], &[0x11, 0x22]);
+}
+
+// Timers and sound
+
+#[test]
+fn _fx07_fx15_fx18_timers() {
+    let mut chip = boot(&[0x6005, 0xF015, 0xF018, 0xF107]);
+    step(&mut chip, 3);
+    assert_eq!((chip.delay_timer(), chip.sound_timer()), (5, 5));
+
+    chip.tick_timers();
+    chip.tick_timers();
+    step(&mut chip, 1);
+    assert_eq!(chip.v()[1], 3);
+
+    let mut audio = Audio::default();
+    chip.play_sound(&mut audio);
+    assert!(audio.tone);
+    for _ in 0..3 {
+        chip.tick_timers();
+    }
+    chip.play_sound(&mut audio);
+    assert!(!audio.tone);
+}
+
+#[test]
+fn _f002_fx3a_set_the_audio_pattern() {
+    let mut ops = vec![0xA208, 0xF002, 0x6050, 0xF03A];
+    ops.extend_from_slice(&[0x0102, 0x0304, 0x0506, 0x0708, 0x090A, 0x0B0C, 0x0D0E, 0x0F10]);
+    let mut chip = boot(&ops);
+    chip.set_xochip(true);
+    step(&mut chip, 4);
+
+    let mut audio = Audio::default();
+    chip.play_sound(&mut audio);
+    let (pattern, pitch) = audio.pattern.unwrap();
+    assert_eq!(pattern, [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16]);
+    assert_eq!(pitch, 0x50);
+}
+
+// Decoding
+
+#[test]
+fn unknown_opcodes_fail() {
+    for &op in &[0x5001, 0x8008, 0xE000, 0xF0FF, 0xF102] {
+        match boot(&[op]).emulate_cycle() {
+            Err(EmuError::InvalidOpcode { pc: 0x200, opcode }) => assert_eq!(opcode, op),
+            r => panic!("expected {:04X} to be invalid, got {:?}", op, r),
+        }
+    }
+}
+
+#[test]
+fn running_off_the_end_of_memory_fails() {
+    let mut chip = boot(&[0x1FFF]);
+    step(&mut chip, 1);
+    match chip.emulate_cycle() {
+        Err(EmuError::PcOutOfBounds { pc: 0xFFF }) => {}
+        r => panic!("expected the PC to run out of memory, got {:?}", r),
+    }
+}
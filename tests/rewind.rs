@@ -0,0 +1,64 @@
+//! Stepping back through the rewind buffer.
+
+extern crate chipate;
+
+mod common;
+
+use chipate::{Chipate, Rewind};
+use common::boot;
+
+/// Counts up in V0 forever, one instruction per value.
+static COUNTER: &[u16] = &[0x7001, 0x1200];
+
+/// Pushes a state with V0 at each of 1 to `n`.
+fn push_counts(rewind: &mut Rewind, chip: &mut Chipate, n: u8) {
+    for _ in 0..n {
+        chip.emulate_cycle().unwrap();
+        chip.emulate_cycle().unwrap();
+        rewind.push(chip);
+    }
+}
+
+#[test]
+fn steps_back_newest_first() {
+    let mut chip = boot(COUNTER);
+    let mut rewind = Rewind::new(10);
+    push_counts(&mut rewind, &mut chip, 3);
+    assert_eq!(rewind.len(), 3);
+
+    for &v0 in &[3, 2, 1] {
+        assert!(rewind.step_back(&mut chip));
+        assert_eq!(chip.v()[0], v0);
+    }
+    assert!(!rewind.step_back(&mut chip));
+    assert!(rewind.is_empty());
+}
+
+#[test]
+fn full_buffer_drops_the_oldest() {
+    let mut chip = boot(COUNTER);
+    let mut rewind = Rewind::new(3);
+    push_counts(&mut rewind, &mut chip, 5);
+    assert_eq!(rewind.len(), 3);
+
+    for &v0 in &[5, 4, 3] {
+        assert!(rewind.step_back(&mut chip));
+        assert_eq!(chip.v()[0], v0);
+    }
+    assert!(!rewind.step_back(&mut chip));
+}
+
+#[test]
+fn zero_capacity_keeps_nothing() {
+    let mut chip = boot(COUNTER);
+    let mut rewind = Rewind::new(0);
+    push_counts(&mut rewind, &mut chip, 2);
+    assert!(rewind.is_empty());
+    assert!(!rewind.step_back(&mut chip));
+}
+
+#[test]
+fn seconds_are_60_frames_and_saturate() {
+    assert_eq!(Rewind::with_seconds(30).capacity(), 1800);
+    assert_eq!(Rewind::with_seconds(u32::MAX).capacity(), u32::MAX as usize);
+}
@@ -0,0 +1,37 @@
+//! Repeatable random numbers for CXNN.
+
+extern crate chipate;
+
+mod common;
+
+use chipate::rng::{SeededRng, SequenceRng};
+use chipate::Rng;
+
+fn draw<R: Rng>(rng: &mut R, n: usize) -> Vec<u8> {
+    (0..n).map(|_| rng.next_u8()).collect()
+}
+
+#[test]
+fn seeded_rng_repeats_for_a_seed() {
+    let first = draw(&mut SeededRng::new(42), 64);
+    assert_eq!(draw(&mut SeededRng::new(42), 64), first);
+    assert_ne!(draw(&mut SeededRng::new(43), 64), first);
+    assert!(first.iter().any(|&b| b != first[0]));
+}
+
+#[test]
+fn sequence_rng_cycles_through_its_bytes() {
+    assert_eq!(draw(&mut SequenceRng::new(&[1, 2, 3]), 7), [1, 2, 3, 1, 2, 3, 1]);
+    assert_eq!(draw(&mut SequenceRng::new(&[]), 3), [0, 0, 0]);
+}
+
+#[test]
+fn cxnn_masks_the_chosen_byte() {
+    let mut chip = common::blank();
+    chip.set_rng(Box::new(SequenceRng::new(&[0xAB, 0xFF])));
+    chip.load_rom_from_bytes(&[0xC0, 0x0F, 0xC1, 0xF0]).unwrap();
+    chip.emulate_cycle().unwrap();
+    chip.emulate_cycle().unwrap();
+    assert_eq!(chip.v()[0], 0x0B);
+    assert_eq!(chip.v()[1], 0xF0);
+}
@@ -0,0 +1,131 @@
+//! Runs the ROMs in tests/roms headless and compares the final screen with
+//! the golden ASCII image next to each of them, `<rom>.txt`.
+//!
+//! The public test suites are not bundled. Once they are copied into
+//! tests/roms under the names below, `cargo test --test roms -- --ignored`
+//! checks them too, failing on any that is missing. Run with
+//! `CHIPATE_BLESS=1` to write the golden images from the current output.
+
+extern crate chipate;
+
+mod common;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use chipate::{asm, dump, Quirks, Scheduler};
+
+struct Rom {
+    /// File in tests/roms, `.8o` sources are assembled first.
+    file: &'static str,
+    quirks: fn() -> Quirks,
+    xochip: bool,
+    frames: usize,
+    /// Written to 0x1FF before starting, the Timendus suite reads the
+    /// platform to test from there instead of asking for a key.
+    platform: Option<u8>,
+}
+
+static OWN_ROMS: &[Rom] = &[
+    Rom { file: "hex_digits.8o", quirks: Quirks::cosmac_vip, xochip: false, frames: 10, platform: None },
+];
+
+static PUBLIC_ROMS: &[Rom] = &[
+    // https://github.com/corax89/chip8-test-rom
+    Rom { file: "test_opcode.ch8", quirks: Quirks::cosmac_vip, xochip: false, frames: 60, platform: None },
+    // https://github.com/Timendus/chip8-test-suite
+    Rom { file: "1-chip8-logo.ch8", quirks: Quirks::cosmac_vip, xochip: false, frames: 60, platform: None },
+    Rom { file: "2-ibm-logo.ch8", quirks: Quirks::cosmac_vip, xochip: false, frames: 60, platform: None },
+    Rom { file: "3-corax+.ch8", quirks: Quirks::cosmac_vip, xochip: false, frames: 60, platform: None },
+    Rom { file: "4-flags.ch8", quirks: Quirks::cosmac_vip, xochip: false, frames: 120, platform: None },
+    Rom { file: "5-quirks.ch8", quirks: Quirks::cosmac_vip, xochip: false, frames: 600, platform: Some(1) },
+    Rom { file: "5-quirks.ch8", quirks: Quirks::superchip, xochip: false, frames: 600, platform: Some(2) },
+    Rom { file: "5-quirks.ch8", quirks: Quirks::xochip, xochip: true, frames: 600, platform: Some(3) },
+];
+
+fn dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("roms")
+}
+
+fn load(path: &Path) -> Result<Vec<u8>, String> {
+    let mut b = Vec::new();
+    File::open(path).and_then(|mut f| f.read_to_end(&mut b)).map_err(|e| e.to_string())?;
+    if path.extension().is_some_and(|e| e == "8o") {
+        let source = String::from_utf8(b).map_err(|e| e.to_string())?;
+        return asm::assemble(&source).map_err(|e| e.to_string());
+    }
+    Ok(b)
+}
+
+/// Runs `rom` and returns the final screen as ASCII.
+fn screen(rom: &Rom, bytes: &[u8]) -> Result<String, String> {
+    let mut chip = common::blank();
+    chip.set_quirks((rom.quirks)());
+    chip.set_xochip(rom.xochip);
+    chip.set_seed(0);
+    chip.load_rom_from_bytes(bytes).map_err(|e| e.to_string())?;
+    if let Some(p) = rom.platform {
+        let mut state = chip.save_state();
+        state.memory[0x1FF] = p;
+        chip.load_state(&state);
+    }
+
+    let mut scheduler = Scheduler::with_ipf(1000);
+    for _ in 0..rom.frames {
+        scheduler.run_frame(&mut chip).map_err(|e| e.to_string())?;
+    }
+    Ok(dump::ascii(&chip.display))
+}
+
+/// Runs each of `roms` and panics listing every one whose screen is off.
+fn check(roms: &[Rom]) {
+    let bless = env::var_os("CHIPATE_BLESS").is_some();
+    let mut failures = Vec::new();
+
+    for rom in roms {
+        let path = dir().join(rom.file);
+        if !path.exists() {
+            failures.push(format!("{}: not found, see tests/roms/README.md", rom.file));
+            continue;
+        }
+        let golden = match rom.platform {
+            Some(p) => dir().join(format!("{}.{}.txt", rom.file, p)),
+            None => dir().join(format!("{}.txt", rom.file)),
+        };
+
+        let got = match load(&path).and_then(|b| screen(rom, &b)) {
+            Ok(s) => s,
+            Err(e) => {
+                failures.push(format!("{}: {}", rom.file, e));
+                continue;
+            }
+        };
+
+        if bless {
+            fs::write(&golden, &got).unwrap();
+            continue;
+        }
+        match fs::read_to_string(&golden) {
+            Ok(ref want) if *want == got => {}
+            Ok(want) => failures.push(format!("{}: screen differs\nwant:\n{}got:\n{}", rom.file, want, got)),
+            Err(_) => failures.push(format!("{}: no golden image {}, run with CHIPATE_BLESS=1 to write it",
+                                            rom.file,
+                                            golden.display())),
+        }
+    }
+
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn own_roms_match_their_golden_images() {
+    check(OWN_ROMS);
+}
+
+#[test]
+#[ignore]
+fn public_suites_match_their_golden_images() {
+    check(PUBLIC_ROMS);
+}
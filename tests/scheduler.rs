@@ -0,0 +1,51 @@
+//! Frame pacing: instructions per frame and the 60 Hz timer tick.
+
+extern crate chipate;
+
+mod common;
+
+use chipate::Scheduler;
+use common::boot;
+
+/// Sets the delay timer to 0x10, then counts V1 up forever.
+static COUNTER: &[u16] = &[0x6010, 0xF015, 0x7101, 0x1204];
+
+#[test]
+fn huge_speeds_saturate_instead_of_overflowing() {
+    assert_eq!(Scheduler::with_ipf(100_000_000).hz(), u32::MAX);
+
+    let mut scheduler = Scheduler::with_hz(u32::MAX);
+    let mut chip = boot(COUNTER);
+    let mut ran = 0;
+    for _ in 0..2 {
+        scheduler.run_frame_until(&mut chip, |_| {
+            ran += 1;
+            ran > 100
+        }).unwrap();
+    }
+    assert!(ran > 100);
+}
+
+#[test]
+fn stopping_mid_frame_keeps_the_timer_tick() {
+    let mut scheduler = Scheduler::with_ipf(10);
+    let mut chip = boot(COUNTER);
+
+    // Stop after 4 instructions, then let the frame finish
+    let mut seen = 0;
+    assert!(scheduler.run_frame_until(&mut chip, |_| {
+        seen += 1;
+        seen == 5
+    }).unwrap());
+    assert_eq!(chip.delay_timer(), 0x10);
+
+    assert!(!scheduler.run_frame_until(&mut chip, |_| false).unwrap());
+    assert_eq!(chip.delay_timer(), 0x0F);
+
+    // The two halves ran one frame of 10 instructions between them
+    let loops = chip.v()[1];
+    scheduler.run_frame(&mut chip).unwrap();
+    assert_eq!(chip.delay_timer(), 0x0E);
+    assert_eq!(chip.v()[1] - loops, 5);
+    assert_eq!(loops, 4);
+}
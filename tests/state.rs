@@ -0,0 +1,109 @@
+//! Save states through their binary form.
+
+extern crate chipate;
+
+mod common;
+
+use chipate::state::VERSION;
+use chipate::{Chipate, EmuError, State};
+
+/// Sets DT, calls a subroutine that draws the font zero and spins there.
+fn running() -> Chipate<'static> {
+    let mut chip = common::boot(&[0x6305, 0xF315, 0x2208, 0x1206, 0xD005, 0x120A]);
+    common::step(&mut chip, 5);
+    chip
+}
+
+/// After the magic, version, flags, quirks, four words, V and the timers.
+const STACK_DEPTH_AT: usize = 4 + 3 + 8 + 16 + 2;
+
+/// Where the memory length sits for a state with `depth` stack entries.
+fn memory_length_at(depth: usize) -> usize {
+    STACK_DEPTH_AT + 1 + 2 * depth + 16 * 3 + 2
+}
+
+fn invalid(bytes: &[u8]) -> String {
+    match State::from_bytes(bytes) {
+        Err(EmuError::InvalidState(reason)) => reason,
+        Err(e) => panic!("expected an invalid state, got {}", e),
+        Ok(_) => panic!("expected an invalid state"),
+    }
+}
+
+#[test]
+fn round_trip_restores_the_machine() {
+    let chip = running();
+    let bytes = chip.save_state().to_bytes();
+    let state = State::from_bytes(&bytes).unwrap();
+    assert_eq!(state.to_bytes(), bytes);
+
+    let mut restored = common::blank();
+    restored.load_state(&state);
+    assert_eq!(restored.pc(), 0x20A);
+    assert_eq!(restored.stack(), chip.stack());
+    assert_eq!(restored.stack().len(), 1);
+    assert_eq!(restored.v(), chip.v());
+    assert_eq!(restored.delay_timer(), 5);
+    assert_eq!(restored.memory(), chip.memory());
+    assert_eq!(restored.display.gfx()[..], chip.display.gfx()[..]);
+}
+
+#[test]
+fn round_trip_keeps_xochip_memory() {
+    let mut chip = running();
+    chip.set_xochip(true);
+    let state = State::from_bytes(&chip.save_state().to_bytes()).unwrap();
+    assert!(state.xochip);
+    assert_eq!(state.memory.len(), 0x10000);
+}
+
+#[test]
+fn rejects_a_bad_magic() {
+    let mut bytes = running().save_state().to_bytes();
+    bytes[0] = b'X';
+    assert_eq!(invalid(&bytes), "not a save state");
+}
+
+#[test]
+fn rejects_an_unknown_version() {
+    let mut bytes = running().save_state().to_bytes();
+    bytes[4] = VERSION + 1;
+    assert_eq!(invalid(&bytes), format!("version {} is not supported, expected {}", VERSION + 1, VERSION));
+}
+
+#[test]
+fn rejects_truncated_input() {
+    let bytes = running().save_state().to_bytes();
+    for &len in &[0, 3, 20, memory_length_at(1) + 100, bytes.len() - 1] {
+        assert_eq!(invalid(&bytes[..len]), "file is truncated", "cut at {}", len);
+    }
+}
+
+#[test]
+fn rejects_a_stack_deeper_than_16() {
+    let mut bytes = running().save_state().to_bytes();
+    bytes[STACK_DEPTH_AT] = 17;
+    assert_eq!(invalid(&bytes), "stack depth 17 is more than 16");
+}
+
+#[test]
+fn rejects_memory_that_does_not_match_the_mode() {
+    let mut bytes = running().save_state().to_bytes();
+    bytes[5] |= 1;
+    assert_eq!(invalid(&bytes), "memory size 4096 does not match XO-CHIP on");
+
+    let mut chip = running();
+    chip.set_xochip(true);
+    let mut bytes = chip.save_state().to_bytes();
+    bytes[5] &= !1;
+    assert_eq!(invalid(&bytes), "memory size 65536 does not match XO-CHIP off");
+}
+
+#[test]
+fn rejects_memory_neither_4k_nor_64k() {
+    let mut bytes = running().save_state().to_bytes();
+    let at = memory_length_at(1);
+    assert_eq!(&bytes[at..at + 4], [0x00, 0x10, 0x00, 0x00]);
+    bytes[at + 1] = 0x20;
+    assert_eq!(invalid(&bytes), "memory size 8192 is neither 4K nor 64K");
+}
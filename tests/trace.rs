@@ -0,0 +1,113 @@
+//! Instruction traces, written by `Tracer` and checked by `Differ`.
+
+extern crate chipate;
+
+mod common;
+
+use chipate::trace::{self, Differ, Format, Step, Tracer};
+use chipate::{Chipate, Quirks, Scheduler};
+use common::boot_with;
+
+/// v0 := 0x06, v1 := 0x03, v0 >>= v1, then loop forever.
+static SHIFT: &[u16] = &[0x6006, 0x6103, 0x8016, 0x1206];
+
+/// Runs `chip` against `reference` until done, at most a few frames.
+fn diff(chip: &mut Chipate, reference: &str) -> Result<usize, trace::Divergence> {
+    let steps = trace::parse_reference(reference).unwrap();
+    let mut differ = Differ::new(&steps);
+    let mut scheduler = Scheduler::with_ipf(10);
+    for _ in 0..5 {
+        if differ.done() {
+            break;
+        }
+        scheduler.run_frame_until(chip, |c| differ.before(c)).unwrap();
+    }
+    differ.finish()
+}
+
+#[test]
+fn text_trace_lists_what_each_instruction_changed() {
+    let mut chip = boot_with(SHIFT, Quirks::cosmac_vip());
+    let mut out = Vec::new();
+    {
+        let mut tracer = Tracer::new(&mut out, Format::Text);
+        for _ in 0..3 {
+            tracer.before(&chip);
+            chip.emulate_cycle().unwrap();
+        }
+        tracer.flush(&chip).unwrap();
+    }
+    let text = String::from_utf8(out).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("0\t0200\t6006\t"));
+    assert!(lines[0].ends_with("\tV0=06"));
+    assert!(lines[2].starts_with("2\t0204\t8016\t"));
+    assert!(lines[2].ends_with("\tV0=01 VF=01"));
+}
+
+#[test]
+fn json_trace_has_one_object_per_instruction() {
+    let mut chip = boot_with(SHIFT, Quirks::cosmac_vip());
+    let mut out = Vec::new();
+    {
+        let mut tracer = Tracer::new(&mut out, Format::Json);
+        tracer.before(&chip);
+        chip.emulate_cycle().unwrap();
+        tracer.flush(&chip).unwrap();
+    }
+    let text = String::from_utf8(out).unwrap();
+    assert!(text.starts_with("{\"cycle\":0,\"pc\":512,\"opcode\":24582,"));
+    assert!(text.ends_with("\"changes\":{\"V0\":6}}\n"));
+}
+
+#[test]
+fn reference_lines_take_several_spellings() {
+    let step = Step::parse(4, "PC: 0x0202 op=6103 V0:06, i:0000 VF:00 cycles 7").unwrap().unwrap();
+    assert_eq!(step.line, 4);
+    assert_eq!(step.pc, Some(0x202));
+    assert_eq!(step.opcode, Some(0x6103));
+    assert_eq!(step.i, Some(0));
+    assert_eq!(step.v[0], Some(6));
+    assert_eq!(step.v[0xF], Some(0));
+    assert_eq!(step.v[1], None);
+
+    assert_eq!(Step::parse(1, "# pc opcode registers").unwrap(), None);
+    assert!(Step::parse(2, "PC:0200 V3:100").is_err());
+    assert!(Step::parse(2, "PC:zz").is_err());
+}
+
+#[test]
+fn matching_reference_passes() {
+    let mut chip = boot_with(SHIFT, Quirks::cosmac_vip());
+    let reference = "PC:0200 OP:6006\n\
+                     PC:0202 OP:6103 V0:06\n\
+                     PC:0204 OP:8016 V0:06 V1:03\n\
+                     PC:0206 OP:1206 V0:01 VF:01\n";
+    assert_eq!(diff(&mut chip, reference), Ok(4));
+}
+
+#[test]
+fn shift_quirk_shows_up_as_a_divergence() {
+    // The reference shifts VX in place, as SUPER-CHIP does
+    let mut chip = boot_with(SHIFT, Quirks::cosmac_vip());
+    let reference = "PC:0200\nPC:0202\nPC:0204\nPC:0206 V0:03 VF:00\n";
+    let d = diff(&mut chip, reference).unwrap_err();
+    assert_eq!(d.step, 3);
+    assert_eq!(d.line, 4);
+    assert!(d.after.unwrap().starts_with("0204 8016"));
+    let fields: Vec<String> = d.mismatches.iter().map(|m| m.to_string()).collect();
+    assert_eq!(fields, ["V0: expected 03, got 01", "VF: expected 00, got 01"]);
+
+    let mut chip = boot_with(SHIFT, Quirks::superchip());
+    assert_eq!(diff(&mut chip, reference), Ok(4));
+}
+
+#[test]
+fn exiting_early_is_a_divergence() {
+    // 00FD exits
+    let mut chip = boot_with(&[0x00FD], Quirks::superchip());
+    let d = diff(&mut chip, "PC:0200\nPC:0202\n").unwrap_err();
+    assert_eq!(d.step, 1);
+    assert!(d.mismatches.is_empty());
+}